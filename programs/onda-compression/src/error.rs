@@ -15,4 +15,94 @@ pub enum OndaSocialError {
   InsufficientPostCapacity,
   #[msg("Unauthorized")]
   Unauthorized,
-}
\ No newline at end of file
+  #[msg("Invalid merkle tree")]
+  InvalidMerkleTree,
+  #[msg("Invalid access policy")]
+  InvalidPolicy,
+  #[msg("Forum config already migrated")]
+  AlreadyMigrated,
+  #[msg("Invalid credential")]
+  InvalidCredential,
+  #[msg("Invalid gate")]
+  InvalidGate,
+  #[msg("Author is banned")]
+  AuthorBanned,
+  #[msg("Posting too frequently")]
+  SlowMode,
+  #[msg("Author activity account required")]
+  MissingAuthorActivity,
+  #[msg("Invalid fee account")]
+  InvalidFeeAccount,
+  #[msg("Insufficient treasury funds")]
+  InsufficientTreasuryFunds,
+  #[msg("Entry bond account required")]
+  MissingEntryBond,
+  #[msg("Entry bond is still locked")]
+  BondLocked,
+  #[msg("Entry type not enabled in this forum")]
+  EntryTypeDisabled,
+  #[msg("Nsfw entries not allowed in this forum")]
+  NsfwNotAllowed,
+  #[msg("Invalid uri rule")]
+  InvalidUriRule,
+  #[msg("Uri scheme not allowed in this forum")]
+  UriSchemeNotAllowed,
+  #[msg("Uri host not allowed in this forum")]
+  UriHostNotAllowed,
+  #[msg("Invalid mime type")]
+  InvalidMimeType,
+  #[msg("Data does not match the entry data hash")]
+  InvalidDataHash,
+  #[msg("Content does not match the entry content digest")]
+  InvalidContentDigest,
+  #[msg("Invalid body")]
+  InvalidBody,
+  #[msg("Body too long")]
+  BodyTooLong,
+  #[msg("Invalid poll")]
+  InvalidPoll,
+  #[msg("Invalid poll option")]
+  InvalidPollOption,
+  #[msg("Poll is closed")]
+  PollClosed,
+  #[msg("Polls cannot be edited")]
+  PollNotEditable,
+  #[msg("Already voted")]
+  AlreadyVoted,
+  #[msg("Invalid entry")]
+  InvalidEntry,
+  #[msg("Comments require a parent proof")]
+  ParentProofRequired,
+  #[msg("Invalid parent entry")]
+  InvalidParent,
+  #[msg("Invalid comment depth")]
+  InvalidCommentDepth,
+  #[msg("Comment nested too deeply")]
+  CommentTooDeep,
+  #[msg("Thread is locked")]
+  ThreadLocked,
+  #[msg("Invalid thread lock account")]
+  InvalidThreadLock,
+  #[msg("Announcement flair requires the forum admin")]
+  AnnouncementFlair,
+  #[msg("Too many pinned entries")]
+  TooManyPins,
+  #[msg("Entry is not pinned")]
+  PinNotFound,
+  #[msg("Not allowed to use this flair")]
+  FlairUnauthorized,
+  #[msg("Edits cannot change the entry type or comment target")]
+  EntryKindChanged,
+  #[msg("Unknown entry types")]
+  InvalidEntryTypes,
+  #[msg("Title limit must be positive")]
+  InvalidTitleLimit,
+  #[msg("Uri limit must be positive")]
+  InvalidUriLimit,
+  #[msg("Flair limit is shorter than existing flair")]
+  InvalidFlairLimit,
+  #[msg("Permit nonce account does not match a permit")]
+  InvalidPermitNonce,
+  #[msg("Permit has already been used")]
+  PermitUsed,
+}
@@ -1,17 +1,33 @@
 use url::Url;
 use anchor_lang::{
     prelude::*,
-    solana_program::keccak,
+    solana_program::{
+        ed25519_program,
+        keccak,
+        sysvar::instructions::load_instruction_at_checked,
+    },
+    Discriminator,
+};
+use anchor_spl::{
+    token_2022,
+    token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use anchor_spl::token::{Mint, TokenAccount};
 use spl_account_compression::{
-    program::SplAccountCompression, wrap_application_data_v1, Node, Noop,
+    program::SplAccountCompression,
+    state::{ConcurrentMerkleTreeHeader, CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1},
+    wrap_application_data_v1, Node, Noop,
+};
+use mpl_bubblegum::{
+    hash_creators,
+    hash_metadata,
+    state::{leaf_schema::LeafSchema as BubblegumLeafSchema, metaplex_adapter::MetadataArgs},
+    utils::get_asset_id,
 };
 use mpl_token_metadata::{
     state::Metadata,
     pda::find_metadata_account
 };
-use gpl_session::{SessionError, SessionToken, session_auth_or, Session};
+use gpl_session::{SessionError, SessionToken, Session};
 
 use crate::{
     error::OndaSocialError,
@@ -22,12 +38,32 @@ pub mod state;
 
 declare_id!("ondaTPaRbk5xRJiqje7DS8n6nFu7Hg6jvKthXNemsHg");
 
+/// Default content limits of a forum, see `ContentLimits`
 pub const MAX_TITLE_LEN: usize = 300;
 pub const MAX_URI_LEN: usize = 128;
 pub const MAX_FLAIR_LEN: usize = 42;
+pub const MAX_BODY_LEN: usize = 280;
+pub const MAX_POLICY_NODES: usize = 32;
+pub const MAX_URI_RULES: usize = 8;
+pub const MAX_MIME_TYPE_LEN: usize = 64;
+pub const MAX_POLL_OPTIONS: usize = 10;
+pub const MAX_POLL_OPTION_LEN: usize = 100;
+
+// Token-2022 mints store extensions as TLV entries after the account type,
+// which follows the base state padded to the size of a token account.
+pub const TOKEN_2022_ACCOUNT_TYPE_INDEX: usize = 165;
+pub const TOKEN_2022_MINT_ACCOUNT_TYPE: u8 = 1;
+pub const GROUP_MEMBER_POINTER_EXTENSION: u16 = 22;
+pub const TOKEN_GROUP_MEMBER_EXTENSION: u16 = 23;
 
 #[derive(Accounts)]
-#[instruction(max_depth: u32, max_buffer_size: u32, flair: Vec<String>, gate: Option<Vec<Gate>>)]
+#[instruction(
+    max_depth: u32,
+    max_buffer_size: u32,
+    flair: Vec<String>,
+    gate: Option<Vec<Gate>>,
+    policy: Option<Vec<PolicyNode>>,
+)]
 pub struct InitForum<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -35,13 +71,65 @@ pub struct InitForum<'info> {
         init,
         seeds = [merkle_tree.key().as_ref()],
         payer = payer,
-        space = ForumConfig::get_size(flair, gate),
+        space = ForumConfig::get_size(flair, &[], &[], gate, policy, &[], 1),
+        bump,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    #[account(zero)]
+    /// CHECK: This account must be all zeros
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AttachTree<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
         bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
     )]
     pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
     #[account(zero)]
     /// CHECK: This account must be all zeros
+    pub new_tree: UncheckedAccount<'info>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddShard<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
     pub merkle_tree: UncheckedAccount<'info>,
+    #[account(zero)]
+    /// CHECK: This account must be all zeros
+    pub new_tree: UncheckedAccount<'info>,
+    #[account(
+        init,
+        seeds = [ForumShard::PREFIX.as_bytes(), merkle_tree.key().as_ref(), new_tree.key().as_ref()],
+        bump,
+        payer = payer,
+        space = ForumShard::SIZE,
+    )]
+    pub shard: Account<'info, ForumShard>,
     pub log_wrapper: Program<'info, Noop>,
     pub compression_program: Program<'info, SplAccountCompression>,
     pub system_program: Program<'info, System>,
@@ -64,6 +152,252 @@ pub struct SetAdmin<'info> {
     pub merkle_tree: UncheckedAccount<'info>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateForumConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BanAuthor<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: the author being banned
+    pub author: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), author.key().as_ref()],
+        bump,
+        payer = payer,
+        space = Ban::SIZE,
+    )]
+    pub ban: Account<'info, Ban>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnbanAuthor<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: receives the ban account's rent
+    pub receiver: UncheckedAccount<'info>,
+    /// CHECK: the author being unbanned
+    pub author: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), author.key().as_ref()],
+        bump,
+        close = receiver,
+    )]
+    pub ban: Account<'info, Ban>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(post: Pubkey)]
+pub struct LockThread<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [ThreadLock::PREFIX.as_bytes(), merkle_tree.key().as_ref(), post.as_ref()],
+        bump,
+        payer = payer,
+        space = ThreadLock::SIZE,
+    )]
+    pub thread_lock: Account<'info, ThreadLock>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(post: Pubkey)]
+pub struct UnlockThread<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: receives the thread lock account's rent
+    pub receiver: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ThreadLock::PREFIX.as_bytes(), merkle_tree.key().as_ref(), post.as_ref()],
+        bump,
+        close = receiver,
+    )]
+    pub thread_lock: Account<'info, ThreadLock>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PinEntry<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [ForumPins::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+        payer = payer,
+        space = ForumPins::SIZE,
+    )]
+    pub forum_pins: Account<'info, ForumPins>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnpinEntry<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ForumPins::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub forum_pins: Account<'info, ForumPins>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTreasuryConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    #[account(
+        init_if_needed,
+        seeds = [ForumTreasury::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+        payer = payer,
+        space = ForumTreasury::SIZE,
+    )]
+    pub treasury: Account<'info, ForumTreasury>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(entry_id: Pubkey)]
+pub struct ReclaimBond<'info> {
+    #[account(mut)]
+    pub author: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [EntryBond::PREFIX.as_bytes(), entry_id.as_ref()],
+        bump,
+        has_one = author @OndaSocialError::Unauthorized,
+        close = author,
+    )]
+    pub entry_bond: Account<'info, EntryBond>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: receives lamport withdrawals
+    pub receiver: UncheckedAccount<'info>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        constraint = forum_config.admin == *admin.key @OndaSocialError::Unauthorized,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    #[account(
+        mut,
+        seeds = [ForumTreasury::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Account<'info, ForumTreasury>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// Token accounts are only required for token withdrawals
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub receiver_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateForumConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        owner = crate::id(),
+    )]
+    /// CHECK: deserialized with the previous layout
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: forum config
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts, Session)]
 pub struct AddEntry<'info> {
     /// CHECK: session auth
@@ -80,35 +414,40 @@ pub struct AddEntry<'info> {
     pub signer: Signer<'info>,
     /// CHECK: check is signer
     pub additional_signer: Option<AccountInfo<'info>>,
+    /// Must be the forum admin, required for announcement flair
+    pub forum_admin: Option<Signer<'info>>,
+    /// CHECK: deserialized if the author has been banned
     #[account(
-        mut,
-        seeds = [merkle_tree.key().as_ref()],
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), author.key().as_ref()],
         bump,
     )]
-    pub forum_config: Account<'info, ForumConfig>,
-    pub mint: Option<Account<'info, Mint>>,
-    /// CHECK: deserialized
-    pub metadata: Option<UncheckedAccount<'info>>,
+    pub ban: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: required when the forum has slow mode enabled, created by the author's first entry
+    pub author_activity: Option<UncheckedAccount<'info>>,
+    /// CHECK: lock of the commented post, deserialized if the post has been locked
+    pub thread_lock: Option<UncheckedAccount<'info>>,
+    /// Posting fee accounts are only required when the forum charges a fee
     #[account(
-        associated_token::mint = mint,
-        associated_token::authority = author,
+        mut,
+        seeds = [ForumTreasury::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
     )]
-    pub token_account: Option<Account<'info, TokenAccount>>,
+    pub treasury: Option<Account<'info, ForumTreasury>>,
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
     #[account(mut)]
-    /// CHECK: constrained by seeds
-    pub merkle_tree: UncheckedAccount<'info>,
-    pub log_wrapper: Program<'info, Noop>,
-    pub compression_program: Program<'info, SplAccountCompression>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct DeleteEntry<'info> {
-    /// CHECK: matches post author
-    pub author: UncheckedAccount<'info>,
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// CHECK: instructions sysvar, read for signed posting permits
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: marks the nonce of the posting permit used, permits are not accepted without it
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
     #[account(
+        mut,
         seeds = [merkle_tree.key().as_ref()],
         bump,
     )]
@@ -116,12 +455,278 @@ pub struct DeleteEntry<'info> {
     #[account(mut)]
     /// CHECK: constrained by seeds
     pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: must be the forum's active tree, required once the forum has rolled over to an attached tree
+    pub active_tree: Option<UncheckedAccount<'info>>,
+    /// CHECK: must belong to the forum, required when a comment's parent is in an attached tree
+    pub parent_tree: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: created when the forum has bond mode enabled
+    pub entry_bond: Option<UncheckedAccount<'info>>,
     pub log_wrapper: Program<'info, Noop>,
     pub compression_program: Program<'info, SplAccountCompression>,
     pub system_program: Program<'info, System>,
 }
 
-#[program]
+/// Like `AddEntry`, but the forum config and tree are only read so that entries added to different
+/// shards do not contend on them
+#[derive(Accounts, Session)]
+pub struct AddShardEntry<'info> {
+    /// CHECK: session auth
+    pub author: UncheckedAccount<'info>,
+    #[session(
+        signer = signer,
+        authority = author.key()
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    /// CHECK: check is signer
+    pub additional_signer: Option<AccountInfo<'info>>,
+    /// Must be the forum admin, required for announcement flair
+    pub forum_admin: Option<Signer<'info>>,
+    /// CHECK: deserialized if the author has been banned
+    #[account(
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), author.key().as_ref()],
+        bump,
+    )]
+    pub ban: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: required when the forum has slow mode enabled, created by the author's first entry
+    pub author_activity: Option<UncheckedAccount<'info>>,
+    /// CHECK: lock of the commented post, deserialized if the post has been locked
+    pub thread_lock: Option<UncheckedAccount<'info>>,
+    /// Posting fee accounts are only required when the forum charges a fee
+    #[account(
+        mut,
+        seeds = [ForumTreasury::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Option<Account<'info, ForumTreasury>>,
+    pub fee_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// CHECK: instructions sysvar, read for signed posting permits
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: marks the nonce of the posting permit used, permits are not accepted without it
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: constrained by seeds
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [ForumShard::PREFIX.as_bytes(), merkle_tree.key().as_ref(), tree.key().as_ref()],
+        bump,
+        has_one = merkle_tree @OndaSocialError::InvalidMerkleTree,
+        has_one = tree @OndaSocialError::InvalidMerkleTree,
+    )]
+    pub shard: Account<'info, ForumShard>,
+    #[account(mut)]
+    /// CHECK: the shard's tree, constrained by the shard's seeds
+    pub tree: UncheckedAccount<'info>,
+    /// CHECK: must belong to the forum, required when a comment's parent is in an attached tree
+    pub parent_tree: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: created when the forum has bond mode enabled
+    pub entry_bond: Option<UncheckedAccount<'info>>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts, Session)]
+pub struct EditEntry<'info> {
+    /// CHECK: session auth
+    pub author: UncheckedAccount<'info>,
+    #[session(
+        signer = signer,
+        authority = author.key()
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    /// CHECK: check is signer, required for flair policies with an additional signer gate
+    pub additional_signer: Option<AccountInfo<'info>>,
+    /// Must be the forum admin, required for announcement flair
+    pub forum_admin: Option<Signer<'info>>,
+    /// CHECK: deserialized if the author has been banned
+    #[account(
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), author.key().as_ref()],
+        bump,
+    )]
+    pub ban: UncheckedAccount<'info>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    #[account(mut)]
+    /// CHECK: constrained by seeds
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: must belong to the forum, required when the entry is in an attached tree
+    pub entry_tree: Option<UncheckedAccount<'info>>,
+    /// CHECK: lock of the commented post, deserialized if the post has been locked
+    pub thread_lock: Option<UncheckedAccount<'info>>,
+    /// CHECK: instructions sysvar, read for signed posting permits
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: marks the nonce of the posting permit used, permits are not accepted without it
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteEntry<'info> {
+    #[account(mut)]
+    /// CHECK: matches post author
+    pub author: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    #[account(mut)]
+    /// CHECK: constrained by seeds
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: must belong to the forum, required when the entry is in an attached tree
+    pub entry_tree: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: derived from the entry id, closed if the entry was posted with a bond
+    pub entry_bond: UncheckedAccount<'info>,
+    /// Receives the bond when the entry is deleted by a moderator or before the bond is reclaimable
+    #[account(
+        mut,
+        seeds = [ForumTreasury::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub treasury: Option<Account<'info, ForumTreasury>>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts, Session)]
+pub struct VotePoll<'info> {
+    /// CHECK: session auth
+    pub voter: UncheckedAccount<'info>,
+    #[session(
+        signer = signer,
+        authority = voter.key()
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    /// CHECK: check is signer
+    pub additional_signer: Option<AccountInfo<'info>>,
+    /// CHECK: deserialized if the voter has been banned
+    #[account(
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), voter.key().as_ref()],
+        bump,
+    )]
+    pub ban: UncheckedAccount<'info>,
+    /// CHECK: instructions sysvar, read for signed posting permits
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: marks the nonce of the posting permit used, permits are not accepted without it
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked against the entry id derived from the poll's leaf
+    pub poll: UncheckedAccount<'info>,
+    #[account(
+        init,
+        seeds = [PollVote::PREFIX.as_bytes(), poll.key().as_ref(), voter.key().as_ref()],
+        bump,
+        payer = signer,
+        space = PollVote::SIZE,
+    )]
+    pub poll_vote: Account<'info, PollVote>,
+    /// Keeps a running count of votes on chain, created by the first vote
+    #[account(
+        init_if_needed,
+        seeds = [PollTally::PREFIX.as_bytes(), poll.key().as_ref()],
+        bump,
+        payer = signer,
+        space = PollTally::SIZE,
+    )]
+    pub poll_tally: Account<'info, PollTally>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: constrained by seeds
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: must belong to the forum, required when the poll is in an attached tree
+    pub entry_tree: Option<UncheckedAccount<'info>>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts, Session)]
+pub struct VoteEntry<'info> {
+    /// CHECK: session auth
+    pub voter: UncheckedAccount<'info>,
+    #[session(
+        signer = signer,
+        authority = voter.key()
+    )]
+    pub session_token: Option<Account<'info, SessionToken>>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    /// CHECK: check is signer
+    pub additional_signer: Option<AccountInfo<'info>>,
+    /// CHECK: deserialized if the voter has been banned
+    #[account(
+        seeds = [Ban::PREFIX.as_bytes(), merkle_tree.key().as_ref(), voter.key().as_ref()],
+        bump,
+    )]
+    pub ban: UncheckedAccount<'info>,
+    /// CHECK: instructions sysvar, read for signed posting permits
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: marks the nonce of the posting permit used, permits are not accepted without it
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked against the entry id derived from the entry's leaf
+    pub entry_id: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [EntryVote::PREFIX.as_bytes(), entry_id.key().as_ref(), voter.key().as_ref()],
+        bump,
+        payer = signer,
+        space = EntryVote::SIZE,
+    )]
+    pub entry_vote: Account<'info, EntryVote>,
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub forum_config: Account<'info, ForumConfig>,
+    /// CHECK: constrained by seeds
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: must belong to the forum, required when the entry is in an attached tree
+    pub entry_tree: Option<UncheckedAccount<'info>>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[program]
 pub mod onda_compression {
     use super::*;
 
@@ -131,19 +736,39 @@ pub mod onda_compression {
         max_buffer_size: u32,
         flair: Vec<String>,
         gate: Option<Vec<Gate>>,
+        policy: Option<Vec<PolicyNode>>,
     ) -> Result<()> {
         let forum_config = &mut ctx.accounts.forum_config;
         let merkle_tree = &ctx.accounts.merkle_tree;
         let seed = merkle_tree.key();
         let seeds = &[seed.as_ref(), &[*ctx.bumps.get("forum_config").unwrap()]];
         let wrapper = &ctx.accounts.log_wrapper;
+
+        let gate = gate.unwrap_or(vec![]);
+        let policy = policy.unwrap_or_else(|| PolicyNode::from_gates(&gate));
+        validate_gates(&gate)?;
+        validate_policy(&policy, gate.len())?;
         
         forum_config.set_inner(ForumConfig {
             admin: ctx.accounts.payer.key(),
             total_capacity: 1 << max_depth,
             post_count: 0,
             flair,
-            gate: gate.unwrap_or(vec![]),
+            announcement_flair: vec![],
+            flair_policies: vec![],
+            gate,
+            policy,
+            slow_mode: None,
+            posting_fee: None,
+            bond_mode: None,
+            content_limits: ContentLimits::default(),
+            strict_comments: None,
+            uri_rules: vec![],
+            trees: vec![ForumTree {
+                merkle_tree: merkle_tree.key(),
+                capacity: 1 << max_depth,
+                shard: false,
+            }],
         });
         
         let authority_pda_signer = &[&seeds[..]];
@@ -163,427 +788,4001 @@ pub mod onda_compression {
         )
     }
 
-    pub fn set_admin(ctx: Context<SetAdmin>) -> Result<()> {
+    pub fn attach_tree(ctx: Context<AttachTree>, max_depth: u32, max_buffer_size: u32) -> Result<()> {
         let forum_config = &mut ctx.accounts.forum_config;
-        forum_config.set_admin(ctx.accounts.new_admin.key());
+        let new_tree = &ctx.accounts.new_tree;
+        let seed = ctx.accounts.merkle_tree.key();
+        let seeds = &[seed.as_ref(), &[*ctx.bumps.get("forum_config").unwrap()]];
+        let capacity = 1 << max_depth;
+
+        forum_config.attach_tree(new_tree.key(), capacity);
+        resize_forum_config(forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        let authority_pda_signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.compression_program.to_account_info(),
+            spl_account_compression::cpi::accounts::Initialize {
+                authority: forum_config.to_account_info(),
+                merkle_tree: new_tree.to_account_info(),
+                noop: ctx.accounts.log_wrapper.to_account_info(),
+            },
+            authority_pda_signer,
+        );
+        spl_account_compression::cpi::init_empty_merkle_tree(
+            cpi_ctx,
+            max_depth,
+            max_buffer_size
+        )?;
+
+        emit!(TreeAttached {
+            merkle_tree: seed,
+            tree: new_tree.key(),
+            capacity,
+        });
+
         Ok(())
     }
 
-    #[session_auth_or(
-        ctx.accounts.author.key() == ctx.accounts.signer.key(),
-        OndaSocialError::Unauthorized
-    )]
-    pub fn add_entry(
-        ctx: Context<AddEntry>,
-        data: DataV1,
-    ) -> Result<()> {
-        let author =  ctx.accounts.author.key();
+    pub fn add_shard(ctx: Context<AddShard>, max_depth: u32, max_buffer_size: u32) -> Result<()> {
         let forum_config = &mut ctx.accounts.forum_config;
-        let forum_config_bump = *ctx.bumps.get("forum_config").unwrap();
-        let merkle_tree = &ctx.accounts.merkle_tree;
-        let log_wrapper = &ctx.accounts.log_wrapper;
-        let compression_program = &ctx.accounts.compression_program;
+        let new_tree = &ctx.accounts.new_tree;
+        let shard = &mut ctx.accounts.shard;
+        let seed = ctx.accounts.merkle_tree.key();
+        let seeds = &[seed.as_ref(), &[*ctx.bumps.get("forum_config").unwrap()]];
+        let capacity = 1 << max_depth;
 
-        let mint = &ctx.accounts.mint;
-        let metadata = &ctx.accounts.metadata;
-        let token_account = &ctx.accounts.token_account;
+        shard.merkle_tree = seed;
+        shard.tree = new_tree.key();
+        shard.total_capacity = capacity;
+        shard.post_count = 0;
 
-        match data.clone() {
-            DataV1::TextPost { title, uri, flair, .. } => {
-                validate_flair(&forum_config, &flair)?;
-                validate_post_schema(&title, &uri)?;
-            },
-            DataV1::ImagePost { title, uri, flair, .. } => {
-                validate_flair(&forum_config, &flair)?;
-                validate_post_schema(&title, &uri)?;
-            },
-            DataV1::LinkPost { title, uri, flair, .. } => {
-                validate_flair(&forum_config, &flair)?;
-                validate_post_schema(&title, &uri)?;
-            },
-            DataV1::VideoPost { title, uri, flair, .. } => {
-                validate_flair(&forum_config, &flair)?;
-                validate_post_schema(&title, &uri)?;
-            },
-            DataV1::Comment { uri, .. } => {
-                require!(is_valid_url(&uri), OndaSocialError::InvalidUri);
-                require_gte!(MAX_URI_LEN, uri.len(), OndaSocialError::InvalidUri);
+        forum_config.add_shard(new_tree.key(), capacity);
+        resize_forum_config(forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        let authority_pda_signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.compression_program.to_account_info(),
+            spl_account_compression::cpi::accounts::Initialize {
+                authority: forum_config.to_account_info(),
+                merkle_tree: new_tree.to_account_info(),
+                noop: ctx.accounts.log_wrapper.to_account_info(),
             },
-        }
+            authority_pda_signer,
+        );
+        spl_account_compression::cpi::init_empty_merkle_tree(
+            cpi_ctx,
+            max_depth,
+            max_buffer_size
+        )?;
 
-        // Check if user is allowed to add an entry to this forum
-        let gates = forum_config.gate.clone();
-        let operation_results = gates.iter().map(|gate| {
-            let addresses = gate.address.clone();
-            let mut operation = OperationResult {
-                operator: gate.operator.clone(),
-                result: false,
-            };
+        emit!(ShardAdded {
+            merkle_tree: seed,
+            tree: new_tree.key(),
+            capacity,
+        });
 
-            match gate.rule_type {
-                Rule::Token => {
-                    if mint.is_some() && token_account.is_some() {
-                        let mint = mint.clone().unwrap();
-                        let token_account = token_account.clone().unwrap();
-    
-                        for address in addresses {
-                            let is_valid = is_valid_token(
-                                &address,
-                                &author,
-                                &mint,
-                                &token_account,
-                                gate.amount
-                            );
-
-                            if is_valid {
-                                operation.result = true;
-                                break;
-                            }
-                        }
-                    }
+        Ok(())
+    }
 
-                },
-                Rule::Nft => {
-                    if mint.is_some() && token_account.is_some() &&  metadata.is_some() {
-                        let mint = mint.clone().unwrap();
-                        let token_account = token_account.clone().unwrap();
-                        let metadata_info = metadata.clone().unwrap();
-    
-                        for address in addresses {
-                            let is_valid = is_valid_token(
-                                &address,
-                                &author,
-                                &mint,
-                                &token_account,
-                                gate.amount
-                            );
-    
-                            if is_valid == false {
-                                break;
-                            }
-    
-                            let is_valid = is_valid_nft(
-                                &address,
-                                &mint,
-                                &metadata_info
-                            );
-    
-                            if is_valid {
-                                operation.result = true;
-                                break;
-                            }
-                        }
-                    }
-                },
-                Rule::CompressedNft => {
-                    // TODO: Implement compressed nft gate
-                },
-                Rule::AdditionalSigner => {
-                    if ctx.accounts.additional_signer.is_some() {
-                        let additional_signer = &ctx.accounts.additional_signer.clone().unwrap();
-                        
-                        if additional_signer.is_signer == false {
-                            operation.result = false;
-                        } else {
-                            for address in addresses {
-                                if additional_signer.key().eq(&address) {
-                                    match gate.operator {
-                                        Operator::Not => {
-                                            operation.result = false;
-                                            break;
-                                        },
-                                        _ => {
-                                            operation.result = true;
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    pub fn set_admin(ctx: Context<SetAdmin>) -> Result<()> {
+        let forum_config = &mut ctx.accounts.forum_config;
+        forum_config.set_admin(ctx.accounts.new_admin.key());
+        Ok(())
+    }
 
-            operation
-        }).collect::<Vec<OperationResult>>();
-                
-        let allow_access = evaluate_operations(operation_results);
-        
-        if allow_access == false {
-            return err!(OndaSocialError::Unauthorized);
-        }
-        
-        let entry_id = get_entry_id(&merkle_tree.key(), forum_config.post_count);
-        let created_at = Clock::get()?.unix_timestamp;
-        let data_hash = keccak::hashv(&[&data.try_to_vec()?]);
-        let leaf = LeafSchema::new_v0(
-            entry_id,
-            author,
-            created_at,
-            None,
-            forum_config.post_count,
-            data_hash.to_bytes(),
-        );
+    pub fn migrate_forum_config(ctx: Context<MigrateForumConfig>) -> Result<()> {
+        let admin = &ctx.accounts.admin;
+        let forum_config_info = ctx.accounts.forum_config.to_account_info();
 
-        wrap_application_data_v1(leaf.to_event().try_to_vec()?, log_wrapper)?;
+        let forum_config = {
+            let data = forum_config_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == ForumConfig::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            require!(
+                ForumConfig::try_deserialize(&mut data.as_ref()).is_err(),
+                OndaSocialError::AlreadyMigrated
+            );
+            ForumConfig::from_v0(
+                ForumConfigV0::deserialize(&mut &data[8..])?,
+                ctx.accounts.merkle_tree.key(),
+            )
+        };
 
-        append_leaf(
-            &merkle_tree.key(),
-            forum_config_bump,
-            &compression_program.to_account_info(),
-            &forum_config.to_account_info(),
-            &merkle_tree.to_account_info(),
-            &log_wrapper.to_account_info(),
-            leaf.to_node(),
+        require_keys_eq!(forum_config.admin, admin.key(), OndaSocialError::Unauthorized);
+        validate_policy(&forum_config.policy, forum_config.gate.len())?;
+
+        resize_account(
+            &forum_config_info,
+            forum_config.size(),
+            &admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
         )?;
 
-        forum_config.increment_post_count();
+        let mut data = forum_config_info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        forum_config.try_serialize(&mut writer)?;
 
         Ok(())
     }
 
-    pub fn delete_entry<'info>(
-        ctx: Context<'_, '_, '_, 'info, DeleteEntry<'info>>,
-        root: [u8; 32],
-        created_at: i64,
-        edited_at: Option<i64>,
-        data_hash: [u8; 32],
-        nonce: u64,
-        index: u32,
-    ) -> Result<()> {
+    pub fn add_flair(ctx: Context<UpdateForumConfig>, flair: String) -> Result<()> {
         let forum_config = &mut ctx.accounts.forum_config;
-        let signer = &ctx.accounts.signer;
-        let author = &ctx.accounts.author;
 
-        if signer.key().eq(&author.key()) == false && forum_config.admin.eq(&signer.key()) == false {
-            return err!(OndaSocialError::Unauthorized);
+        require!(
+            flair.len() <= usize::from(forum_config.content_limits.max_flair_len),
+            OndaSocialError::FlairTooLong
+        );
+        require!(!forum_config.flair.contains(&flair), OndaSocialError::InvalidFlair);
 
-        }
+        forum_config.flair.push(flair.clone());
+        resize_forum_config(&ctx.accounts.forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
 
-        let entry_id = get_entry_id(&ctx.accounts.merkle_tree.key(), nonce);
-        let previous_leaf = LeafSchema::new_v0(
-            entry_id,
-            author.key(),
-            created_at,
-            edited_at,
-            nonce,
-            data_hash,
+        emit!(FlairAdded {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            flair,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_flair(ctx: Context<UpdateForumConfig>, flair: String) -> Result<()> {
+        let forum_config = &mut ctx.accounts.forum_config;
+
+        require!(forum_config.flair.contains(&flair), OndaSocialError::InvalidFlair);
+
+        forum_config.flair.retain(|name| *name != flair);
+        forum_config.announcement_flair.retain(|name| *name != flair);
+        forum_config.flair_policies.retain(|policy| policy.flair != flair);
+        resize_forum_config(&ctx.accounts.forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        emit!(FlairRemoved {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            flair,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the flair reserved for entries co-signed by the admin
+    pub fn set_announcement_flair(ctx: Context<UpdateForumConfig>, announcement_flair: Vec<String>) -> Result<()> {
+        let forum_config = &mut ctx.accounts.forum_config;
+
+        for flair in announcement_flair.iter() {
+            require!(forum_config.flair.contains(flair), OndaSocialError::InvalidFlair);
+        }
+
+        forum_config.announcement_flair = announcement_flair.clone();
+        resize_forum_config(&ctx.accounts.forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        emit!(AnnouncementFlairUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            announcement_flair,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_gates(
+        ctx: Context<UpdateForumConfig>,
+        gate: Vec<Gate>,
+        policy: Option<Vec<PolicyNode>>,
+    ) -> Result<()> {
+        let forum_config = &mut ctx.accounts.forum_config;
+        let policy = policy.unwrap_or_else(|| PolicyNode::from_gates(&gate));
+        validate_gates(&gate)?;
+        validate_policy(&policy, gate.len())?;
+        for flair_policy in forum_config.flair_policies.iter() {
+            validate_policy(&flair_policy.policy, gate.len())?;
+        }
+
+        forum_config.gate = gate.clone();
+        forum_config.policy = policy.clone();
+        resize_forum_config(&ctx.accounts.forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        emit!(GatesUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            gate,
+            policy,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the flair policies, which may only reference the forum's current gates
+    pub fn set_flair_policies(ctx: Context<UpdateForumConfig>, flair_policies: Vec<FlairPolicy>) -> Result<()> {
+        validate_flair_policies(&ctx.accounts.forum_config, &flair_policies)?;
+
+        ctx.accounts.forum_config.flair_policies = flair_policies.clone();
+        resize_forum_config(&ctx.accounts.forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        emit!(FlairPoliciesUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            flair_policies,
+        });
+
+        Ok(())
+    }
+
+    pub fn ban_author(ctx: Context<BanAuthor>, until: Option<i64>, reason: u8) -> Result<()> {
+        let ban = &mut ctx.accounts.ban;
+
+        ban.merkle_tree = ctx.accounts.merkle_tree.key();
+        ban.author = ctx.accounts.author.key();
+        ban.until = until;
+        ban.reason = reason;
+
+        emit!(AuthorBanned {
+            merkle_tree: ban.merkle_tree,
+            author: ban.author,
+            until,
+            reason,
+        });
+
+        Ok(())
+    }
+
+    pub fn unban_author(ctx: Context<UnbanAuthor>) -> Result<()> {
+        emit!(AuthorUnbanned {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            author: ctx.accounts.author.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Rejects new comments on `post`, an entry id from `get_entry_id`
+    pub fn lock_thread(ctx: Context<LockThread>, post: Pubkey, until: Option<i64>) -> Result<()> {
+        let thread_lock = &mut ctx.accounts.thread_lock;
+
+        thread_lock.merkle_tree = ctx.accounts.merkle_tree.key();
+        thread_lock.post = post;
+        thread_lock.until = until;
+
+        emit!(ThreadLocked {
+            merkle_tree: thread_lock.merkle_tree,
+            post,
+            until,
+        });
+
+        Ok(())
+    }
+
+    pub fn unlock_thread(ctx: Context<UnlockThread>, post: Pubkey) -> Result<()> {
+        emit!(ThreadUnlocked {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            post,
+        });
+
+        Ok(())
+    }
+
+    /// Pins `entry`, an entry id from `get_entry_id`, at `position` among the forum's pins
+    pub fn pin_entry(ctx: Context<PinEntry>, entry: Pubkey, position: u8, until: Option<i64>) -> Result<()> {
+        let forum_pins = &mut ctx.accounts.forum_pins;
+
+        forum_pins.merkle_tree = ctx.accounts.merkle_tree.key();
+        let position = forum_pins.pin(entry, position, until, Clock::get()?.unix_timestamp)?;
+
+        emit!(EntryPinned {
+            merkle_tree: forum_pins.merkle_tree,
+            entry,
+            position,
+            until,
+        });
+
+        Ok(())
+    }
+
+    pub fn unpin_entry(ctx: Context<UnpinEntry>, entry: Pubkey) -> Result<()> {
+        ctx.accounts.forum_pins.unpin(&entry)?;
+
+        emit!(EntryUnpinned {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            entry,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_slow_mode(ctx: Context<UpdateForumConfig>, slow_mode: Option<SlowMode>) -> Result<()> {
+        ctx.accounts.forum_config.slow_mode = slow_mode.clone();
+
+        emit!(SlowModeUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            slow_mode,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_content_limits(ctx: Context<UpdateForumConfig>, content_limits: ContentLimits) -> Result<()> {
+        validate_content_limits(&ctx.accounts.forum_config, &content_limits)?;
+        ctx.accounts.forum_config.content_limits = content_limits.clone();
+
+        emit!(ContentLimitsUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            content_limits,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_strict_comments(
+        ctx: Context<UpdateForumConfig>,
+        strict_comments: Option<StrictComments>,
+    ) -> Result<()> {
+        if let Some(strict_comments) = &strict_comments {
+            require_gt!(strict_comments.max_depth, 0, OndaSocialError::InvalidCommentDepth);
+        }
+        ctx.accounts.forum_config.strict_comments = strict_comments.clone();
+
+        emit!(StrictCommentsUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            strict_comments,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_uri_rules(ctx: Context<UpdateForumConfig>, uri_rules: Vec<UriRule>) -> Result<()> {
+        validate_uri_rules(&uri_rules)?;
+
+        ctx.accounts.forum_config.uri_rules = uri_rules.clone();
+        resize_forum_config(&ctx.accounts.forum_config, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+
+        emit!(UriRulesUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            uri_rules,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_posting_fee(ctx: Context<UpdateTreasuryConfig>, posting_fee: Option<PostingFee>) -> Result<()> {
+        ctx.accounts.treasury.merkle_tree = ctx.accounts.merkle_tree.key();
+        ctx.accounts.forum_config.posting_fee = posting_fee.clone();
+
+        emit!(PostingFeeUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            posting_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_bond_mode(ctx: Context<UpdateTreasuryConfig>, bond_mode: Option<BondMode>) -> Result<()> {
+        ctx.accounts.treasury.merkle_tree = ctx.accounts.merkle_tree.key();
+        ctx.accounts.forum_config.bond_mode = bond_mode.clone();
+
+        emit!(BondModeUpdated {
+            merkle_tree: ctx.accounts.merkle_tree.key(),
+            bond_mode,
+        });
+
+        Ok(())
+    }
+
+    pub fn reclaim_bond(ctx: Context<ReclaimBond>, entry_id: Pubkey) -> Result<()> {
+        let entry_bond = &ctx.accounts.entry_bond;
+
+        require!(
+            Clock::get()?.unix_timestamp >= entry_bond.reclaimable_at,
+            OndaSocialError::BondLocked
+        );
+
+        emit!(EntryBondClosed {
+            merkle_tree: entry_bond.merkle_tree,
+            entry_id,
+            receiver: ctx.accounts.author.key(),
+            forfeited: false,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let treasury = &ctx.accounts.treasury;
+        let merkle_tree = &ctx.accounts.merkle_tree;
+
+        let mint = match &ctx.accounts.mint {
+            Some(mint) => {
+                let (Some(treasury_token_account), Some(receiver_token_account), Some(token_program)) = (
+                    &ctx.accounts.treasury_token_account,
+                    &ctx.accounts.receiver_token_account,
+                    &ctx.accounts.token_program,
+                ) else {
+                    return err!(OndaSocialError::InvalidFeeAccount);
+                };
+                require_keys_eq!(treasury_token_account.owner, treasury.key(), OndaSocialError::InvalidFeeAccount);
+                require_keys_eq!(treasury_token_account.mint, mint.key(), OndaSocialError::InvalidFeeAccount);
+
+                let merkle_tree_key = merkle_tree.key();
+                let seeds = &[
+                    ForumTreasury::PREFIX.as_bytes(),
+                    merkle_tree_key.as_ref(),
+                    &[*ctx.bumps.get("treasury").unwrap()],
+                ];
+                let signer_seeds = &[&seeds[..]];
+
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        TransferChecked {
+                            from: treasury_token_account.to_account_info(),
+                            mint: mint.to_account_info(),
+                            to: receiver_token_account.to_account_info(),
+                            authority: treasury.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    amount,
+                    mint.decimals,
+                )?;
+
+                Some(mint.key())
+            },
+            None => {
+                // The treasury has to stay rent exempt
+                let treasury_info = treasury.to_account_info();
+                let rent_exempt_lamports = Rent::get()?.minimum_balance(treasury_info.data_len());
+                let available_lamports = treasury_info.lamports().saturating_sub(rent_exempt_lamports);
+                require_gte!(available_lamports, amount, OndaSocialError::InsufficientTreasuryFunds);
+
+                **treasury_info.try_borrow_mut_lamports()? -= amount;
+                **ctx.accounts.receiver.try_borrow_mut_lamports()? += amount;
+
+                None
+            },
+        };
+
+        emit!(TreasuryWithdrawn {
+            merkle_tree: merkle_tree.key(),
+            mint,
+            amount,
+            receiver: ctx.accounts.receiver.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn add_entry<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddEntry<'info>>,
+        data: DataV1,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        validate_new_entry(
+            &ctx.accounts.forum_config,
+            &ctx.accounts.merkle_tree.key(),
+            ctx.accounts.thread_lock.as_ref(),
+            ctx.accounts.forum_admin.as_ref().map(|admin| admin.key),
+            &data,
+        )?;
+        let remaining_accounts = ctx.remaining_accounts;
+
+        process_add_entry(
+            ctx,
+            remaining_accounts,
+            EntryData::V1 { data },
+            credentials,
+            compressed_nfts,
+            allowlist_proofs,
+        )
+    }
+
+    /// Adds an entry committing to the digest of its off-chain content, or carrying its body inline.
+    /// In forums with strict comments, comments must pass their `parent_entry`.
+    pub fn add_entry_v2<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddEntry<'info>>,
+        data: DataV2,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+        parent_entry: Option<ParentEntry>,
+    ) -> Result<()> {
+        let remaining_accounts = validate_new_entry_v2(
+            &ctx.accounts.forum_config,
+            &ctx.accounts.merkle_tree,
+            ctx.accounts.parent_tree.as_ref(),
+            ctx.accounts.thread_lock.as_ref(),
+            ctx.accounts.forum_admin.as_ref().map(|admin| admin.key),
+            &ctx.accounts.compression_program,
+            &data,
+            parent_entry,
+            ctx.remaining_accounts,
+        )?;
+
+        process_add_entry(
+            ctx,
+            remaining_accounts,
+            EntryData::V2 { data },
+            credentials,
+            compressed_nfts,
+            allowlist_proofs,
+        )
+    }
+
+    /// Adds a `DataV1` or `DataV2` entry to one of the forum's shards, see `add_entry` and `add_entry_v2`
+    pub fn add_shard_entry<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddShardEntry<'info>>,
+        data: EntryData,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+        parent_entry: Option<ParentEntry>,
+    ) -> Result<()> {
+        let remaining_accounts = match &data {
+            EntryData::V1 { data } => {
+                validate_new_entry(
+                    &ctx.accounts.forum_config,
+                    &ctx.accounts.merkle_tree.key(),
+                    ctx.accounts.thread_lock.as_ref(),
+                    ctx.accounts.forum_admin.as_ref().map(|admin| admin.key),
+                    data,
+                )?;
+                ctx.remaining_accounts
+            },
+            EntryData::V2 { data } => validate_new_entry_v2(
+                &ctx.accounts.forum_config,
+                &ctx.accounts.merkle_tree,
+                ctx.accounts.parent_tree.as_ref(),
+                ctx.accounts.thread_lock.as_ref(),
+                ctx.accounts.forum_admin.as_ref().map(|admin| admin.key),
+                &ctx.accounts.compression_program,
+                data,
+                parent_entry,
+                ctx.remaining_accounts,
+            )?,
+        };
+
+        process_add_shard_entry(
+            ctx,
+            remaining_accounts,
+            data,
+            credentials,
+            compressed_nfts,
+            allowlist_proofs,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Replaces an entry with `DataV1` data. `previous_data` must be the entry's current data,
+    /// which the new data may not change the entry type or comment target of. Remaining accounts
+    /// hold the gate accounts, only needed to change to flair with a policy, followed by the leaf proof.
+    pub fn edit_entry<'info>(
+        ctx: Context<'_, '_, '_, 'info, EditEntry<'info>>,
+        root: [u8; 32],
+        created_at: i64,
+        edited_at: Option<i64>,
+        previous_data: EntryData,
+        nonce: u64,
+        index: u32,
+        data: DataV1,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        validate_data(&ctx.accounts.forum_config, &data)?;
+        assert_flair_allowed(
+            &ctx.accounts.forum_config,
+            data.flair(),
+            ctx.accounts.forum_admin.as_ref().map(|admin| admin.key),
+        )?;
+
+        process_edit_entry(
+            ctx,
+            root,
+            created_at,
+            edited_at,
+            previous_data,
+            nonce,
+            index,
+            EntryData::V1 { data },
+            EditGateProofs { credentials, compressed_nfts, allowlist_proofs },
+        )
+    }
+
+    /// Replaces an entry with `DataV2` data, see `edit_entry`
+    #[allow(clippy::too_many_arguments)]
+    pub fn edit_entry_v2<'info>(
+        ctx: Context<'_, '_, '_, 'info, EditEntry<'info>>,
+        root: [u8; 32],
+        created_at: i64,
+        edited_at: Option<i64>,
+        previous_data: EntryData,
+        nonce: u64,
+        index: u32,
+        data: DataV2,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        validate_data_v2(&ctx.accounts.forum_config, &data)?;
+        assert_flair_allowed(
+            &ctx.accounts.forum_config,
+            data.flair(),
+            ctx.accounts.forum_admin.as_ref().map(|admin| admin.key),
+        )?;
+        // Changing the options of a poll would change the meaning of its votes
+        require!(data.entry_type() != ContentLimits::POLL_POST, OndaSocialError::PollNotEditable);
+
+        process_edit_entry(
+            ctx,
+            root,
+            created_at,
+            edited_at,
+            previous_data,
+            nonce,
+            index,
+            EntryData::V2 { data },
+            EditGateProofs { credentials, compressed_nfts, allowlist_proofs },
+        )
+    }
+
+    /// Records one vote per voter on a poll. `data` must be the `DataV2::PollPost` the poll was
+    /// added with. Remaining accounts hold the gate accounts followed by the poll's leaf proof.
+    pub fn vote_poll<'info>(
+        ctx: Context<'_, '_, '_, 'info, VotePoll<'info>>,
+        entry: EntryLeafArgs,
+        data: DataV2,
+        option: u8,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        assert_session_auth_or(
+            ctx.accounts,
+            ctx.accounts.voter.key() == ctx.accounts.signer.key(),
+        )?;
+
+        let voter = ctx.accounts.voter.key();
+        let forum_config = &ctx.accounts.forum_config;
+        let merkle_tree = &ctx.accounts.merkle_tree;
+        let now = Clock::get()?.unix_timestamp;
+
+        let (options, closes_at) = match &data {
+            DataV2::PollPost { options, closes_at, .. } => (options, closes_at),
+            _ => return err!(OndaSocialError::InvalidPoll),
+        };
+        require_gt!(options.len(), usize::from(option), OndaSocialError::InvalidPollOption);
+        if let Some(closes_at) = closes_at {
+            require_gt!(*closes_at, now, OndaSocialError::PollClosed);
+        }
+
+        assert_not_banned(&ctx.accounts.ban, now)?;
+
+        let (gate_accounts, proof) = split_entry_proof(ctx.remaining_accounts, entry.proof_len)?;
+        assert_gate_access(
+            forum_config,
+            &merkle_tree.key(),
+            &voter,
+            &GateAccounts {
+                additional_signer: ctx.accounts.additional_signer.as_ref(),
+                instructions: ctx.accounts.instructions.as_ref(),
+                permit_nonce: ctx.accounts.permit_nonce.as_ref(),
+                payer: &ctx.accounts.signer.to_account_info(),
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            },
+            gate_accounts,
+            &credentials,
+            &compressed_nfts,
+            &allowlist_proofs,
+            None,
+        )?;
+
+        let tree = get_entry_tree(forum_config, merkle_tree, ctx.accounts.entry_tree.as_ref())?;
+        let poll = get_entry_id(&tree.key(), entry.nonce);
+        require_keys_eq!(poll, ctx.accounts.poll.key(), OndaSocialError::InvalidPoll);
+        verify_entry_leaf(
+            &ctx.accounts.compression_program.to_account_info(),
+            &tree,
+            &poll,
+            &entry,
+            keccak::hashv(&[&data.try_to_vec()?]).to_bytes(),
+            proof,
+        )?;
+
+        let poll_vote = &mut ctx.accounts.poll_vote;
+        poll_vote.merkle_tree = merkle_tree.key();
+        poll_vote.poll = poll;
+        poll_vote.voter = voter;
+        poll_vote.option = option;
+        poll_vote.voted_at = now;
+
+        let poll_tally = &mut ctx.accounts.poll_tally;
+        poll_tally.merkle_tree = merkle_tree.key();
+        poll_tally.poll = poll;
+        let option_votes = poll_tally.record_vote(options.len(), option);
+
+        emit!(PollVoted {
+            merkle_tree: merkle_tree.key(),
+            poll,
+            voter,
+            option,
+            option_votes,
+        });
+
+        Ok(())
+    }
+
+    /// Up or downvotes an entry, once per voter. Remaining accounts hold the gate accounts
+    /// followed by the entry's leaf proof.
+    pub fn vote_entry<'info>(
+        ctx: Context<'_, '_, '_, 'info, VoteEntry<'info>>,
+        entry: EntryLeafArgs,
+        data_hash: [u8; 32],
+        direction: VoteDirection,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        assert_session_auth_or(
+            ctx.accounts,
+            ctx.accounts.voter.key() == ctx.accounts.signer.key(),
+        )?;
+
+        let voter = ctx.accounts.voter.key();
+        let forum_config = &ctx.accounts.forum_config;
+        let merkle_tree = &ctx.accounts.merkle_tree;
+        let now = Clock::get()?.unix_timestamp;
+
+        assert_not_banned(&ctx.accounts.ban, now)?;
+
+        let (gate_accounts, proof) = split_entry_proof(ctx.remaining_accounts, entry.proof_len)?;
+        assert_gate_access(
+            forum_config,
+            &merkle_tree.key(),
+            &voter,
+            &GateAccounts {
+                additional_signer: ctx.accounts.additional_signer.as_ref(),
+                instructions: ctx.accounts.instructions.as_ref(),
+                permit_nonce: ctx.accounts.permit_nonce.as_ref(),
+                payer: &ctx.accounts.signer.to_account_info(),
+                compression_program: &ctx.accounts.compression_program.to_account_info(),
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            },
+            gate_accounts,
+            &credentials,
+            &compressed_nfts,
+            &allowlist_proofs,
+            None,
+        )?;
+
+        let tree = get_entry_tree(forum_config, merkle_tree, ctx.accounts.entry_tree.as_ref())?;
+        let entry_id = get_entry_id(&tree.key(), entry.nonce);
+        require_keys_eq!(entry_id, ctx.accounts.entry_id.key(), OndaSocialError::InvalidEntry);
+        verify_entry_leaf(
+            &ctx.accounts.compression_program.to_account_info(),
+            &tree,
+            &entry_id,
+            &entry,
+            data_hash,
+            proof,
+        )?;
+
+        let entry_vote = &mut ctx.accounts.entry_vote;
+        let delta = entry_vote.record_vote(direction, now).ok_or(OndaSocialError::AlreadyVoted)?;
+        entry_vote.merkle_tree = merkle_tree.key();
+        entry_vote.entry = entry_id;
+        entry_vote.voter = voter;
+
+        emit!(EntryScoreChanged {
+            merkle_tree: merkle_tree.key(),
+            entry: entry_id,
+            voter,
+            direction,
+            delta,
+        });
+
+        Ok(())
+    }
+
+    pub fn delete_entry<'info>(
+        ctx: Context<'_, '_, '_, 'info, DeleteEntry<'info>>,
+        root: [u8; 32],
+        created_at: i64,
+        edited_at: Option<i64>,
+        data_hash: [u8; 32],
+        nonce: u64,
+        index: u32,
+    ) -> Result<()> {
+        let forum_config = &mut ctx.accounts.forum_config;
+        let signer = &ctx.accounts.signer;
+        let author = &ctx.accounts.author;
+
+        if signer.key().eq(&author.key()) == false && forum_config.admin.eq(&signer.key()) == false {
+            return err!(OndaSocialError::Unauthorized);
+
+        }
+
+        let tree = get_entry_tree(forum_config, &ctx.accounts.merkle_tree, ctx.accounts.entry_tree.as_ref())?;
+        let entry_id = get_entry_id(&tree.key(), nonce);
+        let previous_leaf = LeafSchema::new_v0(
+            entry_id,
+            author.key(),
+            created_at,
+            edited_at,
+            nonce,
+            data_hash,
+        );
+        let new_leaf = Node::default();
+
+        replace_leaf(
+            &ctx.accounts.merkle_tree.key(),
+            *ctx.bumps.get("forum_config").unwrap(),
+            &ctx.accounts.compression_program.to_account_info(),
+            &ctx.accounts.forum_config.to_account_info(),
+            &tree,
+            &ctx.accounts.log_wrapper.to_account_info(),
+            ctx.remaining_accounts,
+            root,
+            previous_leaf.to_node(),
+            new_leaf,
+            index,
+        )?;
+
+        // The bond PDA is always passed so that a moderator cannot skip forfeiting it
+        let (bond_pda, _) = Pubkey::find_program_address(
+            &[EntryBond::PREFIX.as_bytes(), entry_id.as_ref()],
+            &crate::id(),
+        );
+        let entry_bond = ctx.accounts.entry_bond.to_account_info();
+        require_keys_eq!(entry_bond.key(), bond_pda, OndaSocialError::MissingEntryBond);
+
+        if entry_bond.owner.eq(&crate::id()) && !entry_bond.data_is_empty() {
+            let entry_bond = Account::<EntryBond>::try_from(&entry_bond)?;
+
+            // Bonds are forfeited when a moderator deletes the entry, or when the author deletes it
+            // before the bond could be reclaimed, so that spam cannot be withdrawn ahead of moderators
+            let forfeited = signer.key().ne(&author.key())
+                || Clock::get()?.unix_timestamp < entry_bond.reclaimable_at;
+            let receiver = match forfeited {
+                true => ctx.accounts.treasury
+                    .as_ref()
+                    .ok_or(OndaSocialError::InvalidFeeAccount)?
+                    .to_account_info(),
+                false => author.to_account_info(),
+            };
+
+            emit!(EntryBondClosed {
+                merkle_tree: ctx.accounts.merkle_tree.key(),
+                entry_id,
+                receiver: receiver.key(),
+                forfeited,
+            });
+
+            entry_bond.close(receiver)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `remaining_accounts` holds the gate accounts, see `assert_gate_access`
+pub fn process_add_entry<'c, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, AddEntry<'info>>,
+    remaining_accounts: &'c [AccountInfo<'info>],
+    data: EntryData,
+    credentials: Vec<Credential>,
+    compressed_nfts: Vec<CompressedNftArgs>,
+    allowlist_proofs: Vec<AllowlistProof>,
+) -> Result<()> {
+    assert_session_auth_or(
+        ctx.accounts,
+        ctx.accounts.author.key() == ctx.accounts.signer.key(),
+    )?;
+
+    let forum_config = &mut ctx.accounts.forum_config;
+    let tree = get_entry_tree(forum_config, &ctx.accounts.merkle_tree, ctx.accounts.active_tree.as_ref())?;
+    require!(forum_config.contains_post_capacity(1), OndaSocialError::InsufficientPostCapacity);
+    let active_tree = forum_config.get_active_tree().ok_or(OndaSocialError::InsufficientPostCapacity)?;
+    require_keys_eq!(tree.key(), active_tree.merkle_tree, OndaSocialError::InvalidMerkleTree);
+
+    let nonce = forum_config.post_count;
+    forum_config.increment_post_count();
+
+    let forum_config_bump = *ctx.bumps.get("forum_config").unwrap();
+    append_entry(
+        ctx.accounts.entry_accounts(tree),
+        forum_config_bump,
+        nonce,
+        remaining_accounts,
+        &data,
+        credentials,
+        compressed_nfts,
+        allowlist_proofs,
+    )
+}
+
+/// `remaining_accounts` holds the gate accounts, see `assert_gate_access`
+pub fn process_add_shard_entry<'c, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, AddShardEntry<'info>>,
+    remaining_accounts: &'c [AccountInfo<'info>],
+    data: EntryData,
+    credentials: Vec<Credential>,
+    compressed_nfts: Vec<CompressedNftArgs>,
+    allowlist_proofs: Vec<AllowlistProof>,
+) -> Result<()> {
+    assert_session_auth_or(
+        ctx.accounts,
+        ctx.accounts.author.key() == ctx.accounts.signer.key(),
+    )?;
+
+    let shard = &mut ctx.accounts.shard;
+    require!(shard.contains_post_capacity(1), OndaSocialError::InsufficientPostCapacity);
+
+    let nonce = shard.post_count;
+    shard.increment_post_count();
+
+    let tree = ctx.accounts.tree.to_account_info();
+    let forum_config_bump = *ctx.bumps.get("forum_config").unwrap();
+    append_entry(
+        ctx.accounts.entry_accounts(tree),
+        forum_config_bump,
+        nonce,
+        remaining_accounts,
+        &data,
+        credentials,
+        compressed_nfts,
+        allowlist_proofs,
+    )
+}
+
+/// Appends `data` as the entry with `nonce` to `accounts.tree`, after checking the author may post it
+#[allow(clippy::too_many_arguments)]
+pub fn append_entry<'info>(
+    accounts: EntryAccounts<'_, 'info>,
+    forum_config_bump: u8,
+    nonce: u64,
+    remaining_accounts: &[AccountInfo<'info>],
+    data: &EntryData,
+    credentials: Vec<Credential>,
+    compressed_nfts: Vec<CompressedNftArgs>,
+    allowlist_proofs: Vec<AllowlistProof>,
+) -> Result<()> {
+    let author = accounts.author.key();
+    let forum_config = accounts.forum_config;
+    let merkle_tree = accounts.merkle_tree.key();
+    let tree = &accounts.tree;
+    let log_wrapper = accounts.log_wrapper;
+    let compression_program = accounts.compression_program;
+    let created_at = Clock::get()?.unix_timestamp;
+
+    assert_not_banned(accounts.ban, created_at)?;
+
+    // Check if user is allowed to add an entry to this forum
+    assert_gate_access(
+        forum_config,
+        &merkle_tree,
+        &author,
+        &GateAccounts {
+            additional_signer: accounts.additional_signer,
+            instructions: accounts.instructions,
+            permit_nonce: accounts.permit_nonce,
+            payer: &accounts.signer.to_account_info(),
+            compression_program: &compression_program.to_account_info(),
+            system_program: &accounts.system_program.to_account_info(),
+        },
+        remaining_accounts,
+        &credentials,
+        &compressed_nfts,
+        &allowlist_proofs,
+        data.flair().map(String::as_str),
+    )?;
+
+    let entry_id = get_entry_id(&tree.key(), nonce);
+
+    let is_comment = data.entry_type() == ContentLimits::COMMENT;
+    // The activity account is only read, and paid for, in forums with slow mode enabled
+    if let Some(slow_mode) = &forum_config.slow_mode {
+        let author_activity = accounts.author_activity.ok_or(OndaSocialError::MissingAuthorActivity)?;
+
+        record_author_activity(
+            &author_activity.to_account_info(),
+            &accounts.signer.to_account_info(),
+            &accounts.system_program.to_account_info(),
+            &merkle_tree,
+            &author,
+            slow_mode,
+            is_comment,
+            created_at,
+        )?;
+    }
+
+    let leaf = LeafSchema::new_v0(
+        entry_id,
+        author,
+        created_at,
+        None,
+        nonce,
+        data.data_hash()?,
+    );
+
+    wrap_application_data_v1(leaf.to_event().try_to_vec()?, log_wrapper)?;
+    wrap_entry_body(entry_id, data.body(), log_wrapper)?;
+
+    append_leaf(
+        &merkle_tree,
+        forum_config_bump,
+        &compression_program.to_account_info(),
+        &forum_config.to_account_info(),
+        tree,
+        &log_wrapper.to_account_info(),
+        leaf.to_node(),
+    )?;
+
+    emit!(EntryAdded {
+        merkle_tree,
+        tree: tree.key(),
+        id: entry_id,
+        nonce,
+    });
+
+    if let Some(posting_fee) = &forum_config.posting_fee {
+        collect_posting_fee(&accounts, posting_fee, is_comment)?;
+    }
+
+    if let Some(bond_mode) = &forum_config.bond_mode {
+        let entry_bond = accounts.entry_bond.ok_or(OndaSocialError::MissingEntryBond)?;
+
+        create_entry_bond(
+            &entry_bond.to_account_info(),
+            &accounts.signer.to_account_info(),
+            &accounts.system_program.to_account_info(),
+            &entry_id,
+            EntryBond {
+                merkle_tree,
+                author,
+                nonce,
+                amount: bond_mode.amount,
+                reclaimable_at: created_at.saturating_add(bond_mode.reclaim_window),
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Checks a new `DataV1` entry, besides the gates checked by `append_entry`
+pub fn validate_new_entry(
+    forum_config: &ForumConfig,
+    merkle_tree: &Pubkey,
+    thread_lock: Option<&UncheckedAccount>,
+    forum_admin: Option<&Pubkey>,
+    data: &DataV1,
+) -> Result<()> {
+    validate_data(forum_config, data)?;
+    assert_flair_allowed(forum_config, data.flair(), forum_admin)?;
+    // Only `DataV2` comments record the depth checked by strict comments
+    require!(
+        forum_config.strict_comments.is_none() || data.entry_type() != ContentLimits::COMMENT,
+        OndaSocialError::ParentProofRequired
+    );
+    if let DataV1::Comment { post, .. } = data {
+        assert_thread_unlocked(thread_lock, merkle_tree, post, Clock::get()?.unix_timestamp)?;
+    }
+
+    Ok(())
+}
+
+/// Checks a new `DataV2` entry, besides the gates checked by `append_entry`. In forums with strict
+/// comments the parent proof is split off the end of `remaining_accounts`, and the gate accounts
+/// before it are returned.
+#[allow(clippy::too_many_arguments)]
+pub fn validate_new_entry_v2<'a, 'info>(
+    forum_config: &ForumConfig,
+    merkle_tree: &AccountInfo<'info>,
+    parent_tree: Option<&UncheckedAccount<'info>>,
+    thread_lock: Option<&UncheckedAccount<'info>>,
+    forum_admin: Option<&Pubkey>,
+    compression_program: &AccountInfo<'info>,
+    data: &DataV2,
+    parent_entry: Option<ParentEntry>,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<&'a [AccountInfo<'info>]> {
+    validate_data_v2(forum_config, data)?;
+    assert_flair_allowed(forum_config, data.flair(), forum_admin)?;
+    let now = Clock::get()?.unix_timestamp;
+    if let Some((post, _, _)) = data.comment() {
+        assert_thread_unlocked(thread_lock, &merkle_tree.key(), &post, now)?;
+    }
+    // A poll closing before it is added could never be voted on
+    if let DataV2::PollPost { closes_at: Some(closes_at), .. } = data {
+        require_gt!(*closes_at, now, OndaSocialError::PollClosed);
+    }
+
+    if forum_config.strict_comments.is_none() || data.comment().is_none() {
+        return Ok(remaining_accounts);
+    }
+
+    let parent_entry = parent_entry.ok_or(OndaSocialError::ParentProofRequired)?;
+    let (gate_accounts, proof) = split_entry_proof(remaining_accounts, parent_entry.leaf.proof_len)?;
+    let parent_tree = get_entry_tree(forum_config, merkle_tree, parent_tree)?;
+
+    verify_comment_parent(compression_program, &parent_tree, data, &parent_entry, proof)?;
+
+    Ok(gate_accounts)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_edit_entry<'info>(
+    ctx: Context<'_, '_, '_, 'info, EditEntry<'info>>,
+    root: [u8; 32],
+    created_at: i64,
+    edited_at: Option<i64>,
+    previous_data: EntryData,
+    nonce: u64,
+    index: u32,
+    data: EntryData,
+    gate_proofs: EditGateProofs,
+) -> Result<()> {
+    assert_session_auth_or(
+        ctx.accounts,
+        ctx.accounts.author.key() == ctx.accounts.signer.key(),
+    )?;
+    assert_same_entry_kind(&previous_data, &data)?;
+
+    let author = ctx.accounts.author.key();
+    let forum_config = &ctx.accounts.forum_config;
+    let merkle_tree = &ctx.accounts.merkle_tree;
+    let log_wrapper = &ctx.accounts.log_wrapper;
+    let now = Clock::get()?.unix_timestamp;
+    assert_not_banned(&ctx.accounts.ban, now)?;
+
+    let gate_accounts_len = get_gate_accounts_len(forum_config, &gate_proofs.credentials, &gate_proofs.compressed_nfts)?;
+    require_gte!(ctx.remaining_accounts.len(), gate_accounts_len, OndaSocialError::InvalidCredential);
+    let (gate_accounts, proof) = ctx.remaining_accounts.split_at(gate_accounts_len);
+    assert_edited_flair_access(
+        forum_config,
+        &merkle_tree.key(),
+        &author,
+        &GateAccounts {
+            additional_signer: ctx.accounts.additional_signer.as_ref(),
+            instructions: ctx.accounts.instructions.as_ref(),
+            permit_nonce: ctx.accounts.permit_nonce.as_ref(),
+            payer: &ctx.accounts.signer.to_account_info(),
+            compression_program: &ctx.accounts.compression_program.to_account_info(),
+            system_program: &ctx.accounts.system_program.to_account_info(),
+        },
+        gate_accounts,
+        &gate_proofs,
+        previous_data.flair().map(String::as_str),
+        data.flair().map(String::as_str),
+    )?;
+
+    if let Some((post, _, _)) = data.comment() {
+        assert_thread_unlocked(ctx.accounts.thread_lock.as_ref(), &merkle_tree.key(), &post, now)?;
+    }
+
+    let data_hash = previous_data.data_hash()?;
+    let new_data_hash = data.data_hash()?;
+
+    let tree = get_entry_tree(forum_config, merkle_tree, ctx.accounts.entry_tree.as_ref())?;
+    let entry_id = get_entry_id(&tree.key(), nonce);
+    let previous_leaf = LeafSchema::new_v0(
+        entry_id,
+        author,
+        created_at,
+        edited_at,
+        nonce,
+        data_hash,
+    );
+    let new_leaf = LeafSchema::new_v0(
+        entry_id,
+        author,
+        created_at,
+        Some(now),
+        nonce,
+        new_data_hash,
+    );
+
+    wrap_application_data_v1(new_leaf.to_event().try_to_vec()?, log_wrapper)?;
+    wrap_entry_body(entry_id, data.body(), log_wrapper)?;
+
+    replace_leaf(
+        &merkle_tree.key(),
+        *ctx.bumps.get("forum_config").unwrap(),
+        &ctx.accounts.compression_program.to_account_info(),
+        &forum_config.to_account_info(),
+        &tree,
+        &log_wrapper.to_account_info(),
+        proof,
+        root,
+        previous_leaf.to_node(),
+        new_leaf.to_node(),
+        index,
+    )
+}
+
+/// Edits keep the entry type, and comments keep their post, parent and depth, so that an edit cannot
+/// skip the checks `add_entry` makes on the entry's place in the forum
+pub fn assert_same_entry_kind(previous_data: &EntryData, data: &EntryData) -> Result<()> {
+    require_eq!(previous_data.entry_type(), data.entry_type(), OndaSocialError::EntryKindChanged);
+    require!(previous_data.comment() == data.comment(), OndaSocialError::EntryKindChanged);
+
+    Ok(())
+}
+
+/// Splits off the entry proof, the last `proof_len` accounts, from the gate accounts before it
+pub fn split_entry_proof<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    proof_len: u8,
+) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+    let proof_len = usize::from(proof_len);
+    require_gte!(remaining_accounts.len(), proof_len, OndaSocialError::InvalidMerkleTree);
+    Ok(remaining_accounts.split_at(remaining_accounts.len() - proof_len))
+}
+
+/// Verifies that an entry leaf exists in `tree`
+pub fn verify_entry_leaf<'info>(
+    compression_program: &AccountInfo<'info>,
+    tree: &AccountInfo<'info>,
+    entry_id: &Pubkey,
+    entry: &EntryLeafArgs,
+    data_hash: [u8; 32],
+    proof: &[AccountInfo<'info>],
+) -> Result<()> {
+    let leaf = LeafSchema::new_v0(
+        *entry_id,
+        entry.author,
+        entry.created_at,
+        entry.edited_at,
+        entry.nonce,
+        data_hash,
+    );
+    let cpi_ctx = CpiContext::new(
+        compression_program.clone(),
+        spl_account_compression::cpi::accounts::VerifyLeaf {
+            merkle_tree: tree.clone(),
+        },
+    )
+    .with_remaining_accounts(proof.to_vec());
+
+    spl_account_compression::cpi::verify_leaf(cpi_ctx, entry.root, leaf.to_node(), entry.index)
+}
+
+/// Checks a new comment against its parent entry. The parent must be a post when the comment has
+/// no parent, otherwise a `DataV2` comment on the same post one level up.
+pub fn verify_comment_parent<'info>(
+    compression_program: &AccountInfo<'info>,
+    parent_tree: &AccountInfo<'info>,
+    data: &DataV2,
+    parent_entry: &ParentEntry,
+    proof: &[AccountInfo<'info>],
+) -> Result<()> {
+    let (post, parent, depth) = data.comment().ok_or(OndaSocialError::InvalidParent)?;
+    let parent_id = get_entry_id(&parent_tree.key(), parent_entry.leaf.nonce);
+    require_keys_eq!(parent_id, parent.unwrap_or(post), OndaSocialError::InvalidParent);
+
+    let parent_depth = match &parent_entry.data {
+        EntryData::V2 { data: parent_data } => match parent_data.comment() {
+            Some((parent_post, _, parent_depth)) => {
+                require_keys_eq!(parent_post, post, OndaSocialError::InvalidParent);
+                parent_depth
+            },
+            None => 0,
+        },
+        // The depth of `DataV1` comments is unknown
+        EntryData::V1 { data: DataV1::Comment { .. } } => return err!(OndaSocialError::InvalidParent),
+        EntryData::V1 { .. } => 0,
+    };
+    require!(parent.is_some() == (parent_depth > 0), OndaSocialError::InvalidParent);
+    require!(depth == parent_depth.saturating_add(1), OndaSocialError::InvalidCommentDepth);
+
+    verify_entry_leaf(
+        compression_program,
+        parent_tree,
+        &parent_id,
+        &parent_entry.leaf,
+        parent_entry.data.data_hash()?,
+        proof,
+    )
+}
+
+/// Logs an inline body in its own event, so that the format of `LeafSchemaEvent` stays unchanged
+pub fn wrap_entry_body(id: Pubkey, body: Option<&String>, log_wrapper: &Program<Noop>) -> Result<()> {
+    match body {
+        Some(body) => wrap_application_data_v1(EntryBodyEvent::new(id, body.clone()).try_to_vec()?, log_wrapper),
+        None => Ok(()),
+    }
+}
+
+pub fn append_leaf<'info>(
+    seed: &Pubkey,
+    bump: u8,
+    compression_program: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    log_wrapper: &AccountInfo<'info>,
+    leaf_node: Node,
+) -> Result<()> {
+    let seeds = &[seed.as_ref(), &[bump]];
+    let authority_pda_signer = &[&seeds[..]];
+    let cpi_ctx = CpiContext::new_with_signer(
+        compression_program.clone(),
+        spl_account_compression::cpi::accounts::Modify {
+            authority: authority.clone(),
+            merkle_tree: merkle_tree.clone(),
+            noop: log_wrapper.clone(),
+        },
+        authority_pda_signer,
+    );
+    spl_account_compression::cpi::append(cpi_ctx, leaf_node)
+}
+
+pub fn replace_leaf<'info>(
+    seed: &Pubkey,
+    bump: u8,
+    compression_program: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    log_wrapper: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    root_node: Node,
+    previous_leaf: Node,
+    new_leaf: Node,
+    index: u32,
+) -> Result<()> {
+    let seeds = &[seed.as_ref(), &[bump]];
+    let authority_pda_signer = &[&seeds[..]];
+    let cpi_ctx = CpiContext::new_with_signer(
+        compression_program.clone(),
+        spl_account_compression::cpi::accounts::Modify {
+            authority: authority.clone(),
+            merkle_tree: merkle_tree.clone(),
+            noop: log_wrapper.clone(),
+        },
+        authority_pda_signer,
+    )
+    .with_remaining_accounts(remaining_accounts.to_vec());
+    spl_account_compression::cpi::replace_leaf(cpi_ctx, root_node, previous_leaf, new_leaf, index)
+}
+
+/// Resizes an account owned by this program, topping up rent from `payer` when
+/// it grows and refunding any excess to `payer` when it shrinks.
+pub fn resize_account<'info>(
+    account: &AccountInfo<'info>,
+    new_size: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(new_size);
+    let current_lamports = account.lamports();
+
+    if rent_exempt_lamports > current_lamports {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_exempt_lamports - current_lamports,
+        )?;
+    } else if current_lamports > rent_exempt_lamports {
+        let refund = current_lamports - rent_exempt_lamports;
+        **account.try_borrow_mut_lamports()? -= refund;
+        **payer.try_borrow_mut_lamports()? += refund;
+    }
+
+    account.realloc(new_size, false)?;
+
+    Ok(())
+}
+
+/// Resolves the tree holding an entry, which is the forum's own tree unless `tree` is given
+pub fn get_entry_tree<'info>(
+    config: &ForumConfig,
+    merkle_tree: &AccountInfo<'info>,
+    tree: Option<&UncheckedAccount<'info>>,
+) -> Result<AccountInfo<'info>> {
+    match tree {
+        Some(tree) => {
+            require!(config.contains_tree(&tree.key()), OndaSocialError::InvalidMerkleTree);
+            Ok(tree.to_account_info())
+        },
+        None => Ok(merkle_tree.clone()),
+    }
+}
+
+/// Creates the bond PDA for an entry, funded with its rent and the bond amount
+pub fn create_entry_bond<'info>(
+    entry_bond: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    entry_id: &Pubkey,
+    bond: EntryBond,
+) -> Result<()> {
+    let (bond_pda, bump) = Pubkey::find_program_address(
+        &[EntryBond::PREFIX.as_bytes(), entry_id.as_ref()],
+        &crate::id(),
+    );
+    require_keys_eq!(entry_bond.key(), bond_pda, OndaSocialError::MissingEntryBond);
+
+    let lamports = Rent::get()?
+        .minimum_balance(EntryBond::SIZE)
+        .saturating_add(bond.amount);
+    create_program_account(
+        entry_bond,
+        payer,
+        system_program,
+        &[EntryBond::PREFIX.as_bytes(), entry_id.as_ref(), &[bump]],
+        EntryBond::SIZE,
+        lamports,
+    )?;
+
+    let mut data = entry_bond.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    bond.try_serialize(&mut writer)
+}
+
+/// Checks an entry against the forum's slow mode and records it on the author's activity PDA,
+/// which is created on the author's first entry after slow mode was enabled
+#[allow(clippy::too_many_arguments)]
+pub fn record_author_activity<'info>(
+    author_activity: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    merkle_tree: &Pubkey,
+    author: &Pubkey,
+    slow_mode: &SlowMode,
+    is_comment: bool,
+    now: i64,
+) -> Result<()> {
+    let (activity_pda, bump) = Pubkey::find_program_address(
+        &[AuthorActivity::PREFIX.as_bytes(), merkle_tree.as_ref(), author.as_ref()],
+        &crate::id(),
+    );
+    require_keys_eq!(author_activity.key(), activity_pda, OndaSocialError::MissingAuthorActivity);
+
+    let mut activity = if author_activity.owner.eq(&crate::id()) && !author_activity.data_is_empty() {
+        AuthorActivity::try_deserialize(&mut &author_activity.data.borrow()[..])?
+    } else {
+        create_program_account(
+            author_activity,
+            payer,
+            system_program,
+            &[AuthorActivity::PREFIX.as_bytes(), merkle_tree.as_ref(), author.as_ref(), &[bump]],
+            AuthorActivity::SIZE,
+            Rent::get()?.minimum_balance(AuthorActivity::SIZE),
+        )?;
+
+        AuthorActivity {
+            merkle_tree: *merkle_tree,
+            author: *author,
+            last_post_at: 0,
+            last_comment_at: 0,
+            window_start: 0,
+            window_count: 0,
+        }
+    };
+
+    require!(activity.can_post(slow_mode, is_comment, now), OndaSocialError::SlowMode);
+    activity.record_entry(is_comment, now);
+
+    let mut data = author_activity.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    activity.try_serialize(&mut writer)
+}
+
+/// Creates a PDA owned by this program, funded with `lamports`. Accounts which were sent lamports
+/// ahead of time are topped up and assigned instead, like Anchor's `init`.
+pub fn create_program_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    space: usize,
+    lamports: u64,
+) -> Result<()> {
+    let signer_seeds = &[seeds];
+
+    if account.lamports() == 0 {
+        anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            lamports,
+            space as u64,
+            &crate::id(),
+        )?;
+    } else {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            lamports,
+        )?;
+        anchor_lang::system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::Allocate {
+                    account_to_allocate: account.clone(),
+                },
+                signer_seeds,
+            ),
+            space as u64,
+        )?;
+        anchor_lang::system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::Assign {
+                    account_to_assign: account.clone(),
+                },
+                signer_seeds,
+            ),
+            &crate::id(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Transfers the posting fee from the signer to the forum treasury
+pub fn collect_posting_fee(accounts: &EntryAccounts, posting_fee: &PostingFee, is_comment: bool) -> Result<()> {
+    let amount = posting_fee.get_amount(is_comment);
+
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let treasury = accounts.treasury.ok_or(OndaSocialError::InvalidFeeAccount)?;
+
+    match posting_fee.mint {
+        Some(mint) => {
+            let (Some(fee_mint), Some(fee_token_account), Some(treasury_token_account), Some(token_program)) = (
+                accounts.fee_mint,
+                accounts.fee_token_account,
+                accounts.treasury_token_account,
+                accounts.token_program,
+            ) else {
+                return err!(OndaSocialError::InvalidFeeAccount);
+            };
+            require_keys_eq!(fee_mint.key(), mint, OndaSocialError::InvalidFeeAccount);
+            require_keys_eq!(treasury_token_account.owner, treasury.key(), OndaSocialError::InvalidFeeAccount);
+            require_keys_eq!(treasury_token_account.mint, mint, OndaSocialError::InvalidFeeAccount);
+
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: fee_token_account.to_account_info(),
+                        mint: fee_mint.to_account_info(),
+                        to: treasury_token_account.to_account_info(),
+                        authority: accounts.signer.to_account_info(),
+                    },
+                ),
+                amount,
+                fee_mint.decimals,
+            )
+        },
+        None => {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: accounts.signer.to_account_info(),
+                        to: treasury.to_account_info(),
+                    },
+                ),
+                amount,
+            )
+        },
+    }
+}
+
+/// The ban PDA only exists once an author has been banned
+pub fn assert_not_banned(ban: &AccountInfo, now: i64) -> Result<()> {
+    if ban.owner.ne(&crate::id()) || ban.data_is_empty() {
+        return Ok(());
+    }
+
+    let ban = Ban::try_deserialize(&mut &ban.data.borrow()[..])?;
+    require!(!ban.is_active(now), OndaSocialError::AuthorBanned);
+
+    Ok(())
+}
+
+/// The thread lock PDA only exists once a post has been locked. Comments without the account, or
+/// with an empty account at the lock's address, are treated as unlocked so that existing clients
+/// keep working.
+pub fn assert_thread_unlocked(
+    thread_lock: Option<&UncheckedAccount>,
+    merkle_tree: &Pubkey,
+    post: &Pubkey,
+    now: i64,
+) -> Result<()> {
+    let Some(thread_lock) = thread_lock else {
+        return Ok(());
+    };
+    let (thread_lock_pda, _) = Pubkey::find_program_address(
+        &[ThreadLock::PREFIX.as_bytes(), merkle_tree.as_ref(), post.as_ref()],
+        &crate::id(),
+    );
+    require_keys_eq!(thread_lock.key(), thread_lock_pda, OndaSocialError::InvalidThreadLock);
+
+    if thread_lock.owner.ne(&crate::id()) || thread_lock.data_is_empty() {
+        return Ok(());
+    }
+
+    let thread_lock = ThreadLock::try_deserialize(&mut &thread_lock.data.borrow()[..])?;
+    require!(!thread_lock.is_active(now), OndaSocialError::ThreadLocked);
+
+    Ok(())
+}
+
+pub fn resize_forum_config<'info>(
+    forum_config: &Account<'info, ForumConfig>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    resize_account(
+        &forum_config.to_account_info(),
+        forum_config.size(),
+        &payer.to_account_info(),
+        &system_program.to_account_info(),
+    )
+}
+
+// Same checks as the `session_auth_or` macro, which does not support instructions with explicit lifetimes
+pub fn assert_session_auth_or<'info, T: Session<'info>>(accounts: &T, is_authorized: bool) -> Result<()> {
+    match accounts.session_token() {
+        Some(token) => {
+            require!(accounts.is_valid()?, SessionError::InvalidToken);
+            require_keys_eq!(
+                accounts.session_authority(),
+                token.authority.key(),
+                OndaSocialError::Unauthorized
+            );
+        },
+        None => {
+            require!(is_authorized, OndaSocialError::Unauthorized);
+        }
+    }
+    Ok(())
+}
+
+pub fn get_entry_id(tree_id: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            ENTRY_PREFIX.as_ref(),
+            tree_id.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        &crate::id(),
+    )
+    .0
+}
+
+pub fn user_owns_nft<'info>(
+    user: &Pubkey,
+    mint: &Pubkey,
+    metadata: &Pubkey,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+) -> bool {
+    let (metadata_pda, _) = find_metadata_account(
+        &mint
+      );
+    
+    if metadata_pda != metadata.key() {
+        return false;
+    }
+
+    if token_account.mint != mint.key() {
+        return false;
+    }
+
+    if token_account.amount != 1 {
+        return false;
+    } 
+
+    if token_account.owner != user.key() {
+        return false;
+    }
+
+    true
+}
+
+/// Total number of remaining accounts taken up by `credentials`
+pub fn get_credential_accounts_len(config: &ForumConfig, credentials: &[Credential]) -> Result<usize> {
+    credentials.iter().try_fold(0, |acc, credential| {
+        let gate = config.gate
+            .get(credential.gate as usize)
+            .ok_or(OndaSocialError::InvalidCredential)?;
+        let accounts_len = gate.rule_type.credential_accounts_len();
+        require_gt!(accounts_len, 0, OndaSocialError::InvalidCredential);
+        Ok(acc + accounts_len)
+    })
+}
+
+/// Number of remaining accounts taken by the credential accounts and the accounts of each compressed nft
+pub fn get_gate_accounts_len(
+    config: &ForumConfig,
+    credentials: &[Credential],
+    compressed_nfts: &[CompressedNftArgs],
+) -> Result<usize> {
+    let compressed_nft_accounts_len = compressed_nfts
+        .iter()
+        .map(|args| 1 + usize::from(args.proof_len))
+        .sum::<usize>();
+
+    Ok(get_credential_accounts_len(config, credentials)? + compressed_nft_accounts_len)
+}
+
+/// Accounts read by `append_entry`, borrowed from `AddEntry` or `AddShardEntry`
+pub struct EntryAccounts<'a, 'info> {
+    pub author: &'a UncheckedAccount<'info>,
+    pub signer: &'a Signer<'info>,
+    pub additional_signer: Option<&'a AccountInfo<'info>>,
+    pub ban: &'a UncheckedAccount<'info>,
+    pub author_activity: Option<&'a UncheckedAccount<'info>>,
+    pub treasury: Option<&'a Account<'info, ForumTreasury>>,
+    pub fee_mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    pub fee_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub treasury_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<&'a Interface<'info, TokenInterface>>,
+    pub instructions: Option<&'a UncheckedAccount<'info>>,
+    pub permit_nonce: Option<&'a UncheckedAccount<'info>>,
+    pub forum_config: &'a Account<'info, ForumConfig>,
+    pub merkle_tree: &'a UncheckedAccount<'info>,
+    /// The tree the entry is appended to
+    pub tree: AccountInfo<'info>,
+    pub entry_bond: Option<&'a UncheckedAccount<'info>>,
+    pub log_wrapper: &'a Program<'info, Noop>,
+    pub compression_program: &'a Program<'info, SplAccountCompression>,
+    pub system_program: &'a Program<'info, System>,
+}
+
+impl<'info> AddEntry<'info> {
+    pub fn entry_accounts(&self, tree: AccountInfo<'info>) -> EntryAccounts<'_, 'info> {
+        EntryAccounts {
+            author: &self.author,
+            signer: &self.signer,
+            additional_signer: self.additional_signer.as_ref(),
+            ban: &self.ban,
+            author_activity: self.author_activity.as_ref(),
+            treasury: self.treasury.as_ref(),
+            fee_mint: self.fee_mint.as_ref(),
+            fee_token_account: self.fee_token_account.as_ref(),
+            treasury_token_account: self.treasury_token_account.as_ref(),
+            token_program: self.token_program.as_ref(),
+            instructions: self.instructions.as_ref(),
+            permit_nonce: self.permit_nonce.as_ref(),
+            forum_config: &self.forum_config,
+            merkle_tree: &self.merkle_tree,
+            tree,
+            entry_bond: self.entry_bond.as_ref(),
+            log_wrapper: &self.log_wrapper,
+            compression_program: &self.compression_program,
+            system_program: &self.system_program,
+        }
+    }
+}
+
+impl<'info> AddShardEntry<'info> {
+    pub fn entry_accounts(&self, tree: AccountInfo<'info>) -> EntryAccounts<'_, 'info> {
+        EntryAccounts {
+            author: &self.author,
+            signer: &self.signer,
+            additional_signer: self.additional_signer.as_ref(),
+            ban: &self.ban,
+            author_activity: self.author_activity.as_ref(),
+            treasury: self.treasury.as_ref(),
+            fee_mint: self.fee_mint.as_ref(),
+            fee_token_account: self.fee_token_account.as_ref(),
+            treasury_token_account: self.treasury_token_account.as_ref(),
+            token_program: self.token_program.as_ref(),
+            instructions: self.instructions.as_ref(),
+            permit_nonce: self.permit_nonce.as_ref(),
+            forum_config: &self.forum_config,
+            merkle_tree: &self.merkle_tree,
+            tree,
+            entry_bond: self.entry_bond.as_ref(),
+            log_wrapper: &self.log_wrapper,
+            compression_program: &self.compression_program,
+            system_program: &self.system_program,
+        }
+    }
+}
+
+/// Accounts read by `assert_gate_access`
+pub struct GateAccounts<'a, 'info> {
+    pub additional_signer: Option<&'a AccountInfo<'info>>,
+    pub instructions: Option<&'a UncheckedAccount<'info>>,
+    pub permit_nonce: Option<&'a UncheckedAccount<'info>>,
+    /// Pays for the permit nonce account
+    pub payer: &'a AccountInfo<'info>,
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+/// Evaluates the forum's access policy for `author`, and the policy of `flair` if it has one.
+#[allow(clippy::too_many_arguments)]
+pub fn assert_gate_access<'info>(
+    forum_config: &ForumConfig,
+    merkle_tree: &Pubkey,
+    author: &Pubkey,
+    accounts: &GateAccounts<'_, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    credentials: &[Credential],
+    compressed_nfts: &[CompressedNftArgs],
+    allowlist_proofs: &[AllowlistProof],
+    flair: Option<&str>,
+) -> Result<()> {
+    let gate_results = verify_gate_proofs(
+        forum_config,
+        merkle_tree,
+        author,
+        accounts,
+        remaining_accounts,
+        credentials,
+        compressed_nfts,
+        allowlist_proofs,
+    )?;
+    require!(evaluate_policy(&forum_config.policy, &gate_results), OndaSocialError::Unauthorized);
+    assert_flair_access(forum_config, flair, &gate_results)?;
+
+    Ok(())
+}
+
+/// Checks each gate against the proofs supplied for it, after verifying compressed nfts and consuming
+/// a posting permit. Credential accounts come first in `remaining_accounts`, followed by the accounts
+/// of each compressed nft.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_gate_proofs<'info>(
+    forum_config: &ForumConfig,
+    merkle_tree: &Pubkey,
+    author: &Pubkey,
+    accounts: &GateAccounts<'_, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    credentials: &[Credential],
+    compressed_nfts: &[CompressedNftArgs],
+    allowlist_proofs: &[AllowlistProof],
+) -> Result<Vec<bool>> {
+    let credential_accounts_len = get_credential_accounts_len(forum_config, credentials)?;
+    require_gte!(
+        remaining_accounts.len(),
+        credential_accounts_len,
+        OndaSocialError::InvalidCredential
+    );
+    let (credential_accounts, mut compressed_nft_accounts) = remaining_accounts.split_at(credential_accounts_len);
+
+    // Leaves are verified up front so that a bad proof fails the transaction
+    let mut compressed_nft_collections = vec![];
+    for args in compressed_nfts {
+        let is_compressed_nft_gate = forum_config.gate
+            .get(args.gate as usize)
+            .map_or(false, |gate| gate.rule_type == Rule::CompressedNft);
+        require!(is_compressed_nft_gate, OndaSocialError::InvalidCredential);
+
+        // The cNFT's merkle tree followed by its proof
+        let accounts_len = 1 + args.proof_len as usize;
+        require_gte!(compressed_nft_accounts.len(), accounts_len, OndaSocialError::InvalidCredential);
+        let (nft_accounts, rest) = compressed_nft_accounts.split_at(accounts_len);
+        compressed_nft_accounts = rest;
+
+        let collection = verify_compressed_nft(
+            author,
+            &nft_accounts[0],
+            accounts.compression_program,
+            &nft_accounts[1..],
+            args,
+        )?;
+        if let Some(collection) = collection {
+            compressed_nft_collections.push((args.gate, collection));
+        }
+    }
+
+    // Only the permit whose nonce is consumed counts
+    let permit_signers = match (accounts.instructions, accounts.permit_nonce) {
+        (Some(instructions), Some(permit_nonce)) => {
+            let permits = get_permit_signers(
+                &instructions.to_account_info(),
+                merkle_tree,
+                author,
+                Clock::get()?.unix_timestamp,
+            )?;
+
+            vec![consume_permit(
+                &permit_nonce.to_account_info(),
+                accounts.payer,
+                accounts.system_program,
+                merkle_tree,
+                &permits,
+            )?]
+        },
+        _ => vec![],
+    };
+
+    get_gate_results(
+        forum_config,
+        author,
+        &GateProofs {
+            credentials,
+            credential_accounts,
+            compressed_nft_collections: &compressed_nft_collections,
+            additional_signer: accounts.additional_signer,
+            allowlist_proofs,
+            permit_signers: &permit_signers,
+        },
+    )
+}
+
+pub fn assert_flair_access(config: &ForumConfig, flair: Option<&str>, gate_results: &[bool]) -> Result<()> {
+    if let Some(flair_policy) = flair.and_then(|flair| config.get_flair_policy(flair)) {
+        require!(evaluate_policy(&flair_policy.policy, gate_results), OndaSocialError::FlairUnauthorized);
+    }
+
+    Ok(())
+}
+
+/// Gate proofs supplied with an edit, see `assert_edited_flair_access`
+pub struct EditGateProofs {
+    pub credentials: Vec<Credential>,
+    pub compressed_nfts: Vec<CompressedNftArgs>,
+    pub allowlist_proofs: Vec<AllowlistProof>,
+}
+
+/// Edits are not checked against the forum's access policy, but changing an entry to flair with a
+/// policy requires the same proofs as adding it. Entries keeping their flair need no proofs.
+#[allow(clippy::too_many_arguments)]
+pub fn assert_edited_flair_access<'info>(
+    config: &ForumConfig,
+    merkle_tree: &Pubkey,
+    author: &Pubkey,
+    accounts: &GateAccounts<'_, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    gate_proofs: &EditGateProofs,
+    previous_flair: Option<&str>,
+    flair: Option<&str>,
+) -> Result<()> {
+    if flair == previous_flair || flair.and_then(|flair| config.get_flair_policy(flair)).is_none() {
+        return Ok(());
+    }
+
+    let gate_results = verify_gate_proofs(
+        config,
+        merkle_tree,
+        author,
+        accounts,
+        remaining_accounts,
+        &gate_proofs.credentials,
+        &gate_proofs.compressed_nfts,
+        &gate_proofs.allowlist_proofs,
+    )?;
+    assert_flair_access(config, flair, &gate_results)
+}
+
+/// Everything supplied by a poster to satisfy a forum's gates
+pub struct GateProofs<'a, 'info> {
+    pub credentials: &'a [Credential],
+    pub credential_accounts: &'a [AccountInfo<'info>],
+    /// Verified collections of the compressed nfts supplied for each gate
+    pub compressed_nft_collections: &'a [(u8, Pubkey)],
+    pub additional_signer: Option<&'a AccountInfo<'info>>,
+    pub allowlist_proofs: &'a [AllowlistProof],
+    /// Keys with a valid posting permit for the author in this transaction
+    pub permit_signers: &'a [Pubkey],
+}
+
+/// Checks each gate against the proofs supplied for it
+pub fn get_gate_results<'info>(
+    config: &ForumConfig,
+    author: &Pubkey,
+    proofs: &GateProofs<'_, 'info>,
+) -> Result<Vec<bool>> {
+    let mut gate_results = vec![false; config.gate.len()];
+    let mut offset = 0;
+
+    for credential in proofs.credentials {
+        let index = credential.gate as usize;
+        let gate = &config.gate[index];
+        let accounts_len = gate.rule_type.credential_accounts_len();
+        let accounts = &proofs.credential_accounts[offset..offset + accounts_len];
+        offset += accounts_len;
+
+        if verify_credential(gate, author, accounts)? {
+            gate_results[index] = true;
+        }
+    }
+
+    for (index, gate) in config.gate.iter().enumerate() {
+        match gate.rule_type {
+            Rule::Token | Rule::Nft => {},
+            Rule::CompressedNft => {
+                gate_results[index] = proofs.compressed_nft_collections
+                    .iter()
+                    .any(|(gate_index, collection)| *gate_index as usize == index && gate.address.contains(collection));
+            },
+            Rule::AdditionalSigner => {
+                if let Some(additional_signer) = proofs.additional_signer {
+                    gate_results[index] = additional_signer.is_signer && gate.address.contains(&additional_signer.key());
+                }
+            },
+            Rule::Allowlist => {
+                gate_results[index] = proofs.allowlist_proofs
+                    .iter()
+                    .filter(|allowlist_proof| allowlist_proof.gate as usize == index)
+                    .any(|allowlist_proof| gate.address.iter().any(|root| {
+                        is_valid_allowlist_proof(&root.to_bytes(), author, &allowlist_proof.proof)
+                    }));
+            },
+            Rule::SignedPermit => {
+                gate_results[index] = proofs.permit_signers.iter().any(|signer| gate.address.contains(signer));
+            },
+        }
+    }
+
+    Ok(gate_results)
+}
+
+/// Checks a token or nft gate against its credential accounts: `[mint, token_account]`
+/// for `Rule::Token` and `[mint, token_account, metadata]` for `Rule::Nft`.
+pub fn verify_credential<'info>(
+    gate: &Gate,
+    owner: &Pubkey,
+    accounts: &[AccountInfo<'info>],
+) -> Result<bool> {
+    let mint = InterfaceAccount::<Mint>::try_from(&accounts[0])?;
+    let token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+
+    let is_valid = match gate.rule_type {
+        Rule::Token => gate.address.iter().any(|address| {
+            is_valid_token(address, owner, &mint, &token_account, gate)
+        }),
+        Rule::Nft => gate.address.iter().any(|address| {
+            is_valid_token_account(owner, &mint, &token_account, gate) &&
+            is_valid_nft(address, &mint, &accounts[2])
+        }),
+        _ => false,
+    };
+
+    Ok(is_valid)
+}
+
+/// Verifies that `owner` holds the Bubblegum leaf described by `args` and returns
+/// the leaf's collection if it has been verified.
+pub fn verify_compressed_nft<'info>(
+    owner: &Pubkey,
+    merkle_tree: &AccountInfo<'info>,
+    compression_program: &AccountInfo<'info>,
+    proof: &[AccountInfo<'info>],
+    args: &CompressedNftArgs,
+) -> Result<Option<Pubkey>> {
+    require_keys_eq!(
+        *merkle_tree.owner,
+        spl_account_compression::id(),
+        OndaSocialError::InvalidMerkleTree
+    );
+
+    // Only trees owned by Bubblegum can be trusted to hold verified collections
+    {
+        let merkle_tree_bytes = merkle_tree.try_borrow_data()?;
+        require_gte!(
+            merkle_tree_bytes.len(),
+            CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1,
+            OndaSocialError::InvalidMerkleTree
+        );
+        let header = ConcurrentMerkleTreeHeader::try_from_slice(
+            &merkle_tree_bytes[..CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1]
+        )?;
+        let (tree_authority, _) = Pubkey::find_program_address(
+            &[merkle_tree.key().as_ref()],
+            &mpl_bubblegum::id(),
+        );
+        header.assert_valid_authority(&tree_authority)?;
+    }
+
+    let (leaf, collection) = get_compressed_nft_leaf(&merkle_tree.key(), owner, args)?;
+
+    let cpi_ctx = CpiContext::new(
+        compression_program.clone(),
+        spl_account_compression::cpi::accounts::VerifyLeaf {
+            merkle_tree: merkle_tree.clone(),
+        },
+    )
+    .with_remaining_accounts(proof.to_vec());
+    spl_account_compression::cpi::verify_leaf(cpi_ctx, args.root, leaf, args.index)?;
+
+    Ok(collection)
+}
+
+/// Rebuilds the Bubblegum leaf of a compressed nft held by `owner`, along with its collection
+/// if the collection is verified
+pub fn get_compressed_nft_leaf(
+    merkle_tree: &Pubkey,
+    owner: &Pubkey,
+    args: &CompressedNftArgs,
+) -> Result<(Node, Option<Pubkey>)> {
+    let metadata = MetadataArgs::try_from_slice(&args.metadata)?;
+    let leaf = BubblegumLeafSchema::new_v0(
+        get_asset_id(merkle_tree, args.nonce),
+        *owner,
+        args.delegate,
+        args.nonce,
+        hash_metadata(&metadata)?,
+        hash_creators(&metadata.creators)?,
+    );
+
+    let collection = match metadata.collection {
+        Some(collection) if collection.verified => Some(collection.key),
+        _ => None,
+    };
+
+    Ok((leaf.to_node(), collection))
+}
+
+/// Verifies that `author` is a leaf of the allowlist merkle tree with `root`. Leaves are
+/// `keccak(author)` and each pair of nodes is sorted before hashing, so no leaf index is needed.
+pub fn is_valid_allowlist_proof(root: &[u8; 32], author: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    let leaf = keccak::hashv(&[author.as_ref()]).to_bytes();
+    let computed_root = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+
+    computed_root.eq(root)
+}
+
+/// Returns the keys that signed an unexpired `PostingPermit` for `author` in this forum, with the
+/// permit nonce, using the ed25519 program instructions of the current transaction.
+pub fn get_permit_signers(
+    instructions: &AccountInfo,
+    forum: &Pubkey,
+    author: &Pubkey,
+    now: i64,
+) -> Result<Vec<(Pubkey, u64)>> {
+    let mut signers = vec![];
+    let mut index = 0;
+
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions) {
+        index += 1;
+
+        if instruction.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        for (signer, message) in get_ed25519_signatures(&instruction.data) {
+            let permit = match PostingPermit::try_from_slice(message) {
+                Ok(permit) => permit,
+                Err(_) => continue,
+            };
+
+            if permit.is_valid(forum, author, now) {
+                signers.push((signer, permit.nonce));
+            }
+        }
+    }
+
+    Ok(signers)
+}
+
+/// Creates the `PermitNonce` account of the permit it belongs to, so that the permit can't be used
+/// again, and returns the key that signed the permit.
+pub fn consume_permit<'info>(
+    permit_nonce: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    merkle_tree: &Pubkey,
+    permits: &[(Pubkey, u64)],
+) -> Result<Pubkey> {
+    let (signer, nonce, bump) = permits
+        .iter()
+        .find_map(|(signer, nonce)| {
+            let (permit_nonce_pda, bump) = Pubkey::find_program_address(
+                &[PermitNonce::PREFIX.as_bytes(), merkle_tree.as_ref(), signer.as_ref(), &nonce.to_le_bytes()],
+                &crate::id(),
+            );
+            permit_nonce_pda.eq(permit_nonce.key).then_some((*signer, *nonce, bump))
+        })
+        .ok_or(OndaSocialError::InvalidPermitNonce)?;
+    require!(permit_nonce.data_is_empty(), OndaSocialError::PermitUsed);
+
+    create_program_account(
+        permit_nonce,
+        payer,
+        system_program,
+        &[PermitNonce::PREFIX.as_bytes(), merkle_tree.as_ref(), signer.as_ref(), &nonce.to_le_bytes(), &[bump]],
+        PermitNonce::SIZE,
+        Rent::get()?.minimum_balance(PermitNonce::SIZE),
+    )?;
+
+    let permit_nonce_data = PermitNonce {
+        merkle_tree: *merkle_tree,
+        signer,
+        nonce,
+    };
+    let mut data = permit_nonce.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    permit_nonce_data.try_serialize(&mut writer)?;
+
+    Ok(signer)
+}
+
+/// Parses the public keys and messages verified by an ed25519 program instruction. Only signatures
+/// whose offsets point into the instruction itself are returned.
+pub fn get_ed25519_signatures(data: &[u8]) -> Vec<(Pubkey, &[u8])> {
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_SIZE: usize = 14;
+
+    let read_u16 = |offset: usize| -> Option<u16> {
+        data.get(offset..offset + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+    };
+    let num_signatures = match data.first() {
+        Some(num_signatures) => *num_signatures as usize,
+        None => return vec![],
+    };
+    let mut signatures = vec![];
+
+    for i in 0..num_signatures {
+        let start = OFFSETS_START + i * OFFSETS_SIZE;
+        let offsets = (0..7)
+            .map(|field| read_u16(start + field * 2))
+            .collect::<Option<Vec<u16>>>();
+        let offsets = match offsets {
+            Some(offsets) => offsets,
+            None => break,
+        };
+        let [_, signature_ix, public_key_offset, public_key_ix, message_offset, message_size, message_ix] = offsets[..] else {
+            break;
+        };
+
+        if signature_ix != u16::MAX || public_key_ix != u16::MAX || message_ix != u16::MAX {
+            continue;
+        }
+
+        let public_key_offset = public_key_offset as usize;
+        let message_offset = message_offset as usize;
+        let public_key = data.get(public_key_offset..public_key_offset + 32);
+        let message = data.get(message_offset..message_offset + message_size as usize);
+
+        if let (Some(public_key), Some(message)) = (public_key, message) {
+            signatures.push((Pubkey::try_from(public_key).unwrap(), message));
+        }
+    }
+
+    signatures
+}
+
+pub fn is_valid_url(input: &str) -> bool {
+    match Url::parse(input) {
+        Ok(_) => true,  // The URL is valid.
+        Err(_) => false, // The URL is not valid.
+    }
+}
+
+pub fn is_valid_token(
+    address: &Pubkey,
+    owner: &Pubkey,
+    mint: &InterfaceAccount<Mint>,
+    token_account: &InterfaceAccount<TokenAccount>,
+    gate: &Gate,
+) -> bool {
+    if mint.key().eq(&address) == false {
+        return false;
+    }
+    is_valid_token_account(owner, mint, token_account, gate)
+}
+
+/// Checks the owner holds a balance of `mint` within the gate limits, whatever the mint address
+pub fn is_valid_token_account(
+    owner: &Pubkey,
+    mint: &InterfaceAccount<Mint>,
+    token_account: &InterfaceAccount<TokenAccount>,
+    gate: &Gate,
+) -> bool {
+    if token_account.mint.eq(&mint.key()) == false {
+        return false;
+    }
+    if token_account.owner.eq(&owner) == false {
+        return false;
+    }
+    gate.is_valid_balance(token_account.amount, mint.decimals)
+} 
+
+/// Checks the nft belongs to the `collection`. Token-2022 mints are verified through their
+/// group member extension, which the group update authority must sign. Metadata embedded in
+/// the mint is not enough, since its update authority is set by the mint authority without
+/// signing, so any other mint must have a verified collection in its Metaplex metadata pda.
+pub fn is_valid_nft(
+    collection: &Pubkey,
+    mint: &InterfaceAccount<Mint>,
+    metadata_info: &AccountInfo,
+) -> bool {
+    if is_valid_group_member(collection, &mint.to_account_info()) {
+        return true;
+    }
+
+    if !find_metadata_account(&mint.key()).0.eq(&metadata_info.key()) {
+        return false
+    }
+
+    if !metadata_info.owner.eq(&mpl_token_metadata::id()) {
+        return false
+    }
+
+    let metadata = match Metadata::deserialize(&mut metadata_info.data.borrow().as_ref()) {
+        Ok(metadata) => metadata,
+        Err(_) => return false,
+    };
+
+    if !metadata.mint.eq(&mint.key()) {
+        return false
+    }
+
+    // Check the metadata is verified for this collection
+    let is_valid_collection = match metadata.collection {
+        Some(collection_details) => collection_details.verified == true && collection_details.key.eq(&collection),
+        None => false,
+    };
+
+    is_valid_collection
+}
+
+/// Returns the value of a Token-2022 mint extension, read directly from the TLV data so
+/// that extensions newer than the `spl-token-2022` version used by anchor can be found.
+pub fn get_mint_extension(mint_data: &[u8], extension_type: u16) -> Option<&[u8]> {
+    if mint_data.len() <= TOKEN_2022_ACCOUNT_TYPE_INDEX || mint_data[TOKEN_2022_ACCOUNT_TYPE_INDEX] != TOKEN_2022_MINT_ACCOUNT_TYPE {
+        return None;
+    }
+
+    let mut offset = TOKEN_2022_ACCOUNT_TYPE_INDEX + 1;
+
+    while offset + 4 <= mint_data.len() {
+        let value_type = u16::from_le_bytes([mint_data[offset], mint_data[offset + 1]]);
+        let length = u16::from_le_bytes([mint_data[offset + 2], mint_data[offset + 3]]) as usize;
+        let start = offset + 4;
+
+        if value_type == 0 || start + length > mint_data.len() {
+            return None;
+        }
+        if value_type == extension_type {
+            return Some(&mint_data[start..start + length]);
+        }
+
+        offset = start + length;
+    }
+
+    None
+}
+
+/// Checks that a Token-2022 mint is a member of the `group` through its own group member extension
+pub fn is_valid_group_member(group: &Pubkey, mint_info: &AccountInfo) -> bool {
+    if !mint_info.owner.eq(&token_2022::ID) {
+        return false;
+    }
+
+    let mint_data = mint_info.data.borrow();
+
+    // GroupMemberPointer { authority, member_address }
+    let member_address = get_mint_extension(&mint_data, GROUP_MEMBER_POINTER_EXTENSION)
+        .and_then(|pointer| pointer.get(32..64));
+    // TokenGroupMember { mint, group, member_number }
+    let member = get_mint_extension(&mint_data, TOKEN_GROUP_MEMBER_EXTENSION);
+
+    match (member_address, member) {
+        (Some(member_address), Some(member)) if member.len() >= 64 => {
+            member_address == mint_info.key.as_ref() &&
+            &member[..32] == mint_info.key.as_ref() &&
+            &member[32..64] == group.as_ref()
+        },
+        _ => false,
+    }
+}
+
+/// Announcement flair may only be used when the forum admin co-signs as `forum_admin`
+pub fn assert_flair_allowed(
+    config: &ForumConfig,
+    flair: Option<&String>,
+    forum_admin: Option<&Pubkey>,
+) -> Result<()> {
+    if !flair.map_or(false, |flair| config.announcement_flair.contains(flair)) {
+        return Ok(());
+    }
+
+    require!(forum_admin == Some(&config.admin), OndaSocialError::AnnouncementFlair);
+
+    Ok(())
+}
+
+pub fn validate_flair(config: &ForumConfig, flair: &Option<String>) -> Result<bool> {
+    if flair.is_none() {
+        return Ok(true);
+    }
+
+    let flair = flair.clone().unwrap();
+    require!(
+        flair.len() <= usize::from(config.content_limits.max_flair_len),
+        OndaSocialError::FlairTooLong
+    );
+    require!(config.flair.iter().find(|name| **name == flair).is_some(), OndaSocialError::InvalidFlair);
+    Ok(true)
+}
+
+pub fn validate_uri(config: &ForumConfig, entry_type: u8, uri: &str) -> Result<bool> {
+    require_gte!(usize::from(config.content_limits.max_uri_len), uri.len(), OndaSocialError::InvalidUri);
+    let url = Url::parse(uri).map_err(|_| error!(OndaSocialError::InvalidUri))?;
+
+    let rule = config.uri_rules.iter().enumerate().find(|(_, rule)| rule.applies_to(entry_type));
+    if let Some((index, rule)) = rule {
+        if !rule.allows_scheme(url.scheme()) {
+            msg!("Uri rule {} does not allow scheme {}", index, url.scheme());
+            return err!(OndaSocialError::UriSchemeNotAllowed);
+        }
+        if !rule.allows_host(url.host_str()) {
+            msg!("Uri rule {} does not allow host {}", index, url.host_str().unwrap_or_default());
+            return err!(OndaSocialError::UriHostNotAllowed);
+        }
+    }
+    Ok(true)
+}
+
+pub fn validate_post_schema(config: &ForumConfig, entry_type: u8, title: &str, uri: &str, nsfw: bool) -> Result<bool> {
+    let limits = &config.content_limits;
+    validate_uri(config, entry_type, uri)?;
+    require_gte!(usize::from(limits.max_title_len), title.len(), OndaSocialError::TitleTooLong);
+    require!(!nsfw || limits.allow_nsfw, OndaSocialError::NsfwNotAllowed);
+    Ok(true)
+}
+
+pub fn validate_data(config: &ForumConfig, data: &DataV1) -> Result<bool> {
+    let entry_type = data.entry_type();
+    require!(config.content_limits.is_enabled(entry_type), OndaSocialError::EntryTypeDisabled);
+
+    match data {
+        DataV1::TextPost { title, uri, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            validate_post_schema(config, entry_type, title, uri, *nsfw)?;
+        },
+        DataV1::ImagePost { title, uri, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            validate_post_schema(config, entry_type, title, uri, *nsfw)?;
+        },
+        DataV1::LinkPost { title, uri, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            validate_post_schema(config, entry_type, title, uri, *nsfw)?;
+        },
+        DataV1::VideoPost { title, uri, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            validate_post_schema(config, entry_type, title, uri, *nsfw)?;
+        },
+        DataV1::Comment { uri, .. } => {
+            validate_uri(config, entry_type, uri)?;
+        },
+    }
+    Ok(true)
+}
+
+pub fn validate_content_digest(config: &ForumConfig, entry_type: u8, content: &ContentDigest) -> Result<bool> {
+    validate_uri(config, entry_type, &content.uri)?;
+    require!(
+        !content.mime_type.is_empty() && content.mime_type.len() <= MAX_MIME_TYPE_LEN,
+        OndaSocialError::InvalidMimeType
+    );
+    Ok(true)
+}
+
+pub fn validate_body(limits: &ContentLimits, body: &str) -> Result<bool> {
+    require!(!body.is_empty(), OndaSocialError::InvalidBody);
+    require_gte!(usize::from(limits.max_body_len), body.len(), OndaSocialError::BodyTooLong);
+    Ok(true)
+}
+
+/// Top level comments have a depth of one, replies are deeper
+pub fn validate_comment_depth(config: &ForumConfig, parent: &Option<Pubkey>, depth: u8) -> Result<bool> {
+    require!((depth == 1) == parent.is_none() && depth > 0, OndaSocialError::InvalidCommentDepth);
+    if let Some(strict_comments) = &config.strict_comments {
+        require_gte!(strict_comments.max_depth, depth, OndaSocialError::CommentTooDeep);
+    }
+    Ok(true)
+}
+
+pub fn validate_poll_options(options: &[String]) -> Result<bool> {
+    require!(
+        (2..=MAX_POLL_OPTIONS).contains(&options.len()),
+        OndaSocialError::InvalidPollOption
+    );
+    require!(
+        options.iter().all(|option| !option.is_empty() && option.len() <= MAX_POLL_OPTION_LEN),
+        OndaSocialError::InvalidPollOption
+    );
+    Ok(true)
+}
+
+pub fn validate_data_v2(config: &ForumConfig, data: &DataV2) -> Result<bool> {
+    let limits = &config.content_limits;
+    let entry_type = data.entry_type();
+    require!(limits.is_enabled(entry_type), OndaSocialError::EntryTypeDisabled);
+
+    match data {
+        DataV2::TextPost { title, content, flair, nsfw, .. }
+        | DataV2::ImagePost { title, content, flair, nsfw, .. }
+        | DataV2::LinkPost { title, content, flair, nsfw, .. }
+        | DataV2::VideoPost { title, content, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            validate_post_schema(config, entry_type, title, &content.uri, *nsfw)?;
+            validate_content_digest(config, entry_type, content)?;
+        },
+        DataV2::Comment { parent, depth, content, .. } => {
+            validate_comment_depth(config, parent, *depth)?;
+            validate_content_digest(config, entry_type, content)?;
+        },
+        DataV2::ShortPost { title, body, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            require_gte!(usize::from(limits.max_title_len), title.len(), OndaSocialError::TitleTooLong);
+            require!(!nsfw || limits.allow_nsfw, OndaSocialError::NsfwNotAllowed);
+            validate_body(limits, body)?;
+        },
+        DataV2::ShortComment { parent, depth, body, .. } => {
+            validate_comment_depth(config, parent, *depth)?;
+            validate_body(limits, body)?;
+        },
+        DataV2::PollPost { title, options, flair, nsfw, .. } => {
+            validate_flair(config, flair)?;
+            require_gte!(usize::from(limits.max_title_len), title.len(), OndaSocialError::TitleTooLong);
+            require!(!nsfw || limits.allow_nsfw, OndaSocialError::NsfwNotAllowed);
+            validate_poll_options(options)?;
+        },
+    }
+    Ok(true)
+}
+
+/// Verifies content fetched from an entry's uri against its leaf. `data` must be the
+/// `DataV2` the entry was added or last edited with. Inline entries have no off-chain content.
+pub fn verify_entry_content(leaf: &LeafSchema, data: &DataV2, content: &[u8]) -> Result<()> {
+    let data_hash = keccak::hashv(&[&data.try_to_vec()?]).to_bytes();
+    require!(data_hash == leaf.data_hash(), OndaSocialError::InvalidDataHash);
+
+    let digest = data.content().ok_or(OndaSocialError::InvalidContentDigest)?;
+    require!(digest.matches(content), OndaSocialError::InvalidContentDigest);
+    Ok(())
+}
+
+/// Content limits must leave at least one known entry type enabled, allow posts to have a title and
+/// uri, and keep the forum's flair usable
+pub fn validate_content_limits(config: &ForumConfig, content_limits: &ContentLimits) -> Result<bool> {
+    require!(content_limits.entry_types != 0, OndaSocialError::EntryTypeDisabled);
+    require!(
+        content_limits.entry_types & !ContentLimits::ALL_ENTRY_TYPES == 0,
+        OndaSocialError::InvalidEntryTypes
+    );
+    require_gt!(content_limits.max_title_len, 0, OndaSocialError::InvalidTitleLimit);
+    require_gt!(content_limits.max_uri_len, 0, OndaSocialError::InvalidUriLimit);
+    require!(
+        config.flair.iter().all(|flair| flair.len() <= usize::from(content_limits.max_flair_len)),
+        OndaSocialError::InvalidFlairLimit
+    );
+    Ok(true)
+}
+
+/// Each rule must cover at least one entry type and no entry type may be covered twice
+pub fn validate_uri_rules(uri_rules: &[UriRule]) -> Result<bool> {
+    require_gte!(MAX_URI_RULES, uri_rules.len(), OndaSocialError::InvalidUriRule);
+
+    let mut covered = 0u8;
+    for rule in uri_rules {
+        require!(rule.entry_types != 0, OndaSocialError::InvalidUriRule);
+        require!(covered & rule.entry_types == 0, OndaSocialError::InvalidUriRule);
+        covered |= rule.entry_types;
+    }
+    Ok(true)
+}
+
+pub fn validate_flair_policies(config: &ForumConfig, flair_policies: &[FlairPolicy]) -> Result<bool> {
+    for (position, flair_policy) in flair_policies.iter().enumerate() {
+        require!(config.flair.contains(&flair_policy.flair), OndaSocialError::InvalidFlair);
+        require!(
+            flair_policies[..position].iter().all(|other| other.flair != flair_policy.flair),
+            OndaSocialError::InvalidFlair
+        );
+        require!(!flair_policy.policy.is_empty(), OndaSocialError::InvalidPolicy);
+        validate_policy(&flair_policy.policy, config.gate.len())?;
+    }
+    Ok(true)
+}
+
+pub fn validate_gates(gates: &[Gate]) -> Result<bool> {
+    for gate in gates {
+        if let Some(max_amount) = gate.max_amount {
+            require_gte!(max_amount, gate.min_amount, OndaSocialError::InvalidGate);
+        }
+    }
+    Ok(true)
+}
+
+pub fn validate_policy(policy: &[PolicyNode], gate_count: usize) -> Result<bool> {
+    require_gte!(MAX_POLICY_NODES, policy.len(), OndaSocialError::InvalidPolicy);
+
+    // Children must come after their parent, which rules out cycles and lets
+    // the policy be evaluated in a single reverse pass.
+    let is_valid_child = |parent: usize, child: &u8| {
+        let child = *child as usize;
+        child > parent && child < policy.len()
+    };
+
+    for (position, node) in policy.iter().enumerate() {
+        let is_valid = match node {
+            PolicyNode::Gate { index } => (*index as usize) < gate_count,
+            PolicyNode::And { children } | PolicyNode::Or { children } => {
+                !children.is_empty() && children.iter().all(|child| is_valid_child(position, child))
+            },
+            PolicyNode::Not { child } => is_valid_child(position, child),
+        };
+        require!(is_valid, OndaSocialError::InvalidPolicy);
+    }
+
+    Ok(true)
+}
+
+pub fn evaluate_policy(policy: &[PolicyNode], gate_results: &[bool]) -> bool {
+    if policy.is_empty() {
+        return true;
+    }
+
+    let mut results = vec![false; policy.len()];
+    let result_of = |results: &[bool], child: &u8| results.get(*child as usize).copied().unwrap_or(false);
+
+    for (position, node) in policy.iter().enumerate().rev() {
+        let result = match node {
+            PolicyNode::Gate { index } => result_of(gate_results, index),
+            PolicyNode::And { children } => children.iter().all(|child| result_of(&results, child)),
+            PolicyNode::Or { children } => children.iter().any(|child| result_of(&results, child)),
+            PolicyNode::Not { child } => !result_of(&results, child),
+        };
+        results[position] = result;
+    }
+
+    results[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_account_compression::ConcurrentMerkleTree;
+    use mpl_bubblegum::state::metaplex_adapter::{Collection, Creator, TokenProgramVersion, TokenStandard};
+
+    fn gate(operator: Operator) -> Gate {
+        Gate {
+            min_amount: 1,
+            max_amount: None,
+            decimals: None,
+            rule_type: Rule::AdditionalSigner,
+            operator,
+            address: vec![],
+        }
+    }
+
+    /// Empty forum with a capacity of 8 entries
+    fn test_forum_config() -> ForumConfig {
+        ForumConfig::from_v0(
+            ForumConfigV0 {
+                total_capacity: 8,
+                post_count: 0,
+                admin: Pubkey::new_unique(),
+                flair: vec![],
+                gate: vec![],
+            },
+            Pubkey::new_unique(),
+        )
+    }
+
+    /// Every combination of `count` gate results
+    fn truth_table(count: usize) -> Vec<Vec<bool>> {
+        (0..1u32 << count)
+            .map(|row| (0..count).map(|bit| row & (1 << bit) != 0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn empty_policy_allows_access() {
+        assert!(evaluate_policy(&[], &[]));
+        assert!(evaluate_policy(&[], &[false]));
+    }
+
+    #[test]
+    fn gate_node() {
+        let policy = vec![PolicyNode::Gate { index: 0 }];
+
+        for row in truth_table(1) {
+            assert_eq!(evaluate_policy(&policy, &row), row[0]);
+        }
+    }
+
+    #[test]
+    fn not_node() {
+        let policy = vec![
+            PolicyNode::Not { child: 1 },
+            PolicyNode::Gate { index: 0 },
+        ];
+
+        for row in truth_table(1) {
+            assert_eq!(evaluate_policy(&policy, &row), !row[0]);
+        }
+    }
+
+    #[test]
+    fn and_node() {
+        let policy = vec![
+            PolicyNode::And { children: vec![1, 2, 3] },
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Gate { index: 1 },
+            PolicyNode::Gate { index: 2 },
+        ];
+
+        for row in truth_table(3) {
+            assert_eq!(evaluate_policy(&policy, &row), row[0] && row[1] && row[2], "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn or_node() {
+        let policy = vec![
+            PolicyNode::Or { children: vec![1, 2, 3] },
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Gate { index: 1 },
+            PolicyNode::Gate { index: 2 },
+        ];
+
+        for row in truth_table(3) {
+            assert_eq!(evaluate_policy(&policy, &row), row[0] || row[1] || row[2], "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn nested_groups() {
+        // (A AND B) OR (C AND NOT D)
+        let policy = vec![
+            PolicyNode::Or { children: vec![1, 4] },
+            PolicyNode::And { children: vec![2, 3] },
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Gate { index: 1 },
+            PolicyNode::And { children: vec![5, 6] },
+            PolicyNode::Gate { index: 2 },
+            PolicyNode::Not { child: 7 },
+            PolicyNode::Gate { index: 3 },
+        ];
+        assert!(validate_policy(&policy, 4).is_ok());
+
+        for row in truth_table(4) {
+            let expected = (row[0] && row[1]) || (row[2] && !row[3]);
+            assert_eq!(evaluate_policy(&policy, &row), expected, "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn shared_children() {
+        // A AND NOT (A AND B), with A referenced twice
+        let policy = vec![
+            PolicyNode::And { children: vec![1, 2] },
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Not { child: 3 },
+            PolicyNode::And { children: vec![1, 4] },
+            PolicyNode::Gate { index: 1 },
+        ];
+        assert!(validate_policy(&policy, 2).is_err());
+
+        let policy = vec![
+            PolicyNode::And { children: vec![1, 2] },
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Not { child: 3 },
+            PolicyNode::And { children: vec![4, 5] },
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Gate { index: 1 },
+        ];
+        assert!(validate_policy(&policy, 2).is_ok());
+
+        for row in truth_table(2) {
+            let expected = row[0] && !row[1];
+            assert_eq!(evaluate_policy(&policy, &row), expected, "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn missing_gate_result_fails_closed() {
+        let policy = vec![PolicyNode::Gate { index: 1 }];
+        assert!(!evaluate_policy(&policy, &[true]));
+
+        let policy = vec![
+            PolicyNode::Not { child: 1 },
+            PolicyNode::Gate { index: 1 },
+        ];
+        assert!(evaluate_policy(&policy, &[true]));
+    }
+
+    #[test]
+    fn validates_policy() {
+        assert!(validate_policy(&[], 0).is_ok());
+
+        // Gate index out of range
+        assert!(validate_policy(&[PolicyNode::Gate { index: 1 }], 1).is_err());
+        // Empty groups
+        assert!(validate_policy(&[PolicyNode::And { children: vec![] }], 0).is_err());
+        assert!(validate_policy(&[PolicyNode::Or { children: vec![] }], 0).is_err());
+        // Self reference
+        assert!(validate_policy(&[PolicyNode::Not { child: 0 }], 0).is_err());
+        // Child out of range
+        assert!(validate_policy(&[PolicyNode::Not { child: 1 }], 0).is_err());
+        // Child before parent
+        let policy = vec![
+            PolicyNode::Gate { index: 0 },
+            PolicyNode::Or { children: vec![0] },
+        ];
+        assert!(validate_policy(&policy, 1).is_err());
+        // Too many nodes
+        let policy = vec![PolicyNode::Gate { index: 0 }; MAX_POLICY_NODES + 1];
+        assert!(validate_policy(&policy, 1).is_err());
+    }
+
+    #[test]
+    fn policy_from_gates() {
+        assert_eq!(PolicyNode::from_gates(&[]), vec![]);
+
+        let gates = vec![
+            gate(Operator::Or),
+            gate(Operator::And),
+            gate(Operator::Not),
+            gate(Operator::Or),
+        ];
+        let policy = PolicyNode::from_gates(&gates);
+        assert!(validate_policy(&policy, gates.len()).is_ok());
+
+        for row in truth_table(4) {
+            let expected = (row[0] || row[3]) && row[1] && !row[2];
+            assert_eq!(evaluate_policy(&policy, &row), expected, "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn policy_from_single_operator_gates() {
+        for operator in [Operator::And, Operator::Or, Operator::Not] {
+            let gates = vec![gate(operator.clone()), gate(operator.clone())];
+            let policy = PolicyNode::from_gates(&gates);
+            assert!(validate_policy(&policy, gates.len()).is_ok());
+
+            for row in truth_table(2) {
+                let expected = match operator {
+                    Operator::And => row[0] && row[1],
+                    Operator::Or => row[0] || row[1],
+                    Operator::Not => !row[0] && !row[1],
+                };
+                assert_eq!(evaluate_policy(&policy, &row), expected, "{:?} {:?}", operator, row);
+            }
+        }
+    }
+
+    #[test]
+    fn forum_config_size_matches_serialized_size() {
+        // Sizes assume optional gate fields are set
+        let mut gates = vec![gate(Operator::Or), gate(Operator::Not)];
+        for gate in gates.iter_mut() {
+            gate.max_amount = Some(10);
+            gate.decimals = Some(2);
+        }
+        let flair = vec!["test".to_string(), "test2".to_string()];
+        let uri_rules = vec![UriRule {
+            entry_types: ContentLimits::IMAGE_POST,
+            schemes: vec!["https".to_string(), "ar".to_string()],
+            hosts: vec!["example.com".to_string()],
+        }];
+        let config = ForumConfig {
+            total_capacity: 1 << 14,
+            post_count: 0,
+            admin: Pubkey::new_unique(),
+            flair: flair.clone(),
+            announcement_flair: vec!["test2".to_string()],
+            flair_policies: vec![FlairPolicy {
+                flair: "test".to_string(),
+                policy: vec![PolicyNode::Not { child: 1 }, PolicyNode::Gate { index: 0 }],
+            }],
+            gate: gates.clone(),
+            policy: PolicyNode::from_gates(&gates),
+            slow_mode: Some(SlowMode::default()),
+            posting_fee: Some(PostingFee {
+                mint: Some(Pubkey::new_unique()),
+                post_amount: 1,
+                comment_amount: 0,
+            }),
+            bond_mode: Some(BondMode {
+                amount: 1,
+                reclaim_window: 60,
+            }),
+            content_limits: ContentLimits::default(),
+            strict_comments: Some(StrictComments { max_depth: 4 }),
+            uri_rules: uri_rules.clone(),
+            trees: vec![
+                ForumTree { merkle_tree: Pubkey::new_unique(), capacity: 1 << 14, shard: false },
+                ForumTree { merkle_tree: Pubkey::new_unique(), capacity: 1 << 14, shard: false },
+            ],
+        };
+
+        assert_eq!(
+            ForumConfig::get_size(
+                flair,
+                &config.announcement_flair,
+                &config.flair_policies,
+                Some(gates),
+                None,
+                &uri_rules,
+                2,
+            ),
+            8 + config.try_to_vec().unwrap().len(),
+        );
+    }
+
+    #[test]
+    fn migrates_v0_forum_config() {
+        let gate_v0 = GateV0 {
+            amount: 5,
+            rule_type: Rule::Token,
+            operator: Operator::And,
+            address: vec![Pubkey::new_unique()],
+        };
+        let config = ForumConfigV0 {
+            total_capacity: 1 << 14,
+            post_count: 3,
+            admin: Pubkey::new_unique(),
+            flair: vec!["test".to_string()],
+            gate: vec![gate_v0.clone()],
+        };
+        let merkle_tree = Pubkey::new_unique();
+        let migrated = ForumConfig::from_v0(config.clone(), merkle_tree);
+
+        assert_eq!(migrated.admin, config.admin);
+        assert_eq!(migrated.post_count, config.post_count);
+        assert_eq!(migrated.trees, vec![ForumTree { merkle_tree, capacity: 1 << 14, shard: false }]);
+        assert_eq!(migrated.gate, vec![Gate::from_v0(gate_v0)]);
+        assert_eq!(migrated.policy, PolicyNode::from_gates(&migrated.gate));
+    }
+
+    #[test]
+    fn migrates_v0_gate_amount_to_min_balance() {
+        let gate = Gate::from_v0(GateV0 {
+            amount: 5,
+            rule_type: Rule::Token,
+            operator: Operator::Or,
+            address: vec![],
+        });
+        assert_eq!(gate.min_amount, 5);
+        assert_eq!(gate.max_amount, None);
+        assert_eq!(gate.decimals, None);
+        assert!(!gate.is_valid_balance(4, 0));
+        assert!(gate.is_valid_balance(5, 0));
+        assert!(gate.is_valid_balance(u64::MAX, 0));
+
+        // A zero amount still requires holding the token
+        let gate = Gate::from_v0(GateV0 {
+            amount: 0,
+            rule_type: Rule::Token,
+            operator: Operator::Or,
+            address: vec![],
+        });
+        assert_eq!(gate.min_amount, 1);
+        assert_eq!(gate.max_amount, None);
+        assert!(!gate.is_valid_balance(0, 0));
+        assert!(gate.is_valid_balance(1, 0));
+    }
+
+    #[test]
+    fn zero_min_balance() {
+        let mut gate = gate(Operator::Or);
+        gate.min_amount = 0;
+        gate.max_amount = Some(0);
+
+        assert!(gate.is_valid_balance(0, 6));
+        assert!(!gate.is_valid_balance(1, 6));
+    }
+
+    #[test]
+    fn raw_balance_bounds() {
+        let mut gate = gate(Operator::Or);
+        gate.min_amount = 10;
+        gate.max_amount = Some(20);
+
+        assert!(!gate.is_valid_balance(9, 6));
+        assert!(gate.is_valid_balance(10, 6));
+        assert!(gate.is_valid_balance(20, 6));
+        assert!(!gate.is_valid_balance(21, 6));
+    }
+
+    #[test]
+    fn decimal_balance_bounds() {
+        // Between 1.5 and 100 tokens
+        let mut gate = gate(Operator::Or);
+        gate.min_amount = 15;
+        gate.max_amount = Some(1000);
+        gate.decimals = Some(1);
+
+        assert!(!gate.is_valid_balance(1_499_999, 6));
+        assert!(gate.is_valid_balance(1_500_000, 6));
+        assert!(gate.is_valid_balance(100_000_000, 6));
+        assert!(!gate.is_valid_balance(100_000_001, 6));
+
+        // Same bounds for a mint without decimals
+        assert!(!gate.is_valid_balance(1, 0));
+        assert!(gate.is_valid_balance(2, 0));
+        assert!(gate.is_valid_balance(100, 0));
+        assert!(!gate.is_valid_balance(101, 0));
+
+        // Bounds with more precision than the mint
+        gate.min_amount = 1_500_000_000;
+        gate.max_amount = None;
+        gate.decimals = Some(9);
+        assert!(!gate.is_valid_balance(14, 1));
+        assert!(gate.is_valid_balance(15, 1));
+    }
+
+    #[test]
+    fn overflowing_balance_bounds_fail_closed() {
+        let mut gate = gate(Operator::Or);
+        gate.min_amount = 1;
+        gate.decimals = Some(u8::MAX);
+        assert!(!gate.is_valid_balance(u64::MAX, 0));
+
+        gate.decimals = Some(0);
+        assert!(!gate.is_valid_balance(u64::MAX, u8::MAX));
+    }
+
+    #[test]
+    fn validates_gates() {
+        let mut gate = gate(Operator::Or);
+        gate.min_amount = 10;
+        gate.max_amount = Some(9);
+        assert!(validate_gates(&[gate.clone()]).is_err());
+
+        gate.max_amount = Some(10);
+        assert!(validate_gates(&[gate]).is_ok());
+    }
+
+    fn compressed_nft_args(collection: Option<Collection>) -> CompressedNftArgs {
+        let metadata = MetadataArgs {
+            name: "Onda".to_string(),
+            symbol: "ONDA".to_string(),
+            uri: "https://onda.community/nft.json".to_string(),
+            seller_fee_basis_points: 0,
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(TokenStandard::NonFungible),
+            collection,
+            uses: None,
+            token_program_version: TokenProgramVersion::Original,
+            creators: vec![Creator { address: Pubkey::new_unique(), verified: false, share: 100 }],
+        };
+
+        CompressedNftArgs {
+            gate: 0,
+            root: [0; 32],
+            delegate: Pubkey::new_unique(),
+            nonce: 0,
+            index: 0,
+            metadata: metadata.try_to_vec().unwrap(),
+            proof_len: 3,
+        }
+    }
+
+    fn compressed_nft_gate_results(collection: Option<Pubkey>, gate_collection: Pubkey) -> Vec<bool> {
+        let collections = collection.map(|collection| (0, collection)).into_iter().collect::<Vec<_>>();
+        compressed_nft_gates_results(&collections, &[gate_collection])
+    }
+
+    /// Results of one `Rule::CompressedNft` gate per collection in `gate_collections`
+    fn compressed_nft_gates_results(collections: &[(u8, Pubkey)], gate_collections: &[Pubkey]) -> Vec<bool> {
+        let mut config = test_forum_config();
+        config.gate = gate_collections.iter().map(|gate_collection| {
+            let mut compressed_nft_gate = gate(Operator::And);
+            compressed_nft_gate.rule_type = Rule::CompressedNft;
+            compressed_nft_gate.address = vec![*gate_collection];
+            compressed_nft_gate
+        }).collect();
+
+        get_gate_results(
+            &config,
+            &Pubkey::new_unique(),
+            &GateProofs {
+                credentials: &[],
+                credential_accounts: &[],
+                compressed_nft_collections: collections,
+                additional_signer: None,
+                allowlist_proofs: &[],
+                permit_signers: &[],
+            },
+        ).unwrap()
+    }
+
+    #[test]
+    fn verifies_compressed_nfts() {
+        let merkle_tree = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let mut args = compressed_nft_args(Some(Collection { verified: true, key: collection }));
+
+        let (leaf, verified_collection) = get_compressed_nft_leaf(&merkle_tree, &owner, &args).unwrap();
+        assert_eq!(verified_collection, Some(collection));
+
+        let sibling = keccak::hashv(&[b"sibling"]).to_bytes();
+        let mut tree = ConcurrentMerkleTree::<3, 8>::new();
+        tree.initialize().unwrap();
+        tree.append(leaf).unwrap();
+        tree.append(sibling).unwrap();
+
+        let empty_level_1 = keccak::hashv(&[&[0; 32], &[0; 32]]).to_bytes();
+        let empty_level_2 = keccak::hashv(&[&empty_level_1, &empty_level_1]).to_bytes();
+        let proof = [sibling, empty_level_1, empty_level_2];
+        args.root = tree.get_root();
+        assert!(tree.prove_leaf(args.root, leaf, &proof, args.index).is_ok());
+        assert!(compressed_nft_gate_results(verified_collection, collection)[0]);
+
+        // Leaf of the same nft held by another owner
+        let (other_leaf, _) = get_compressed_nft_leaf(&merkle_tree, &Pubkey::new_unique(), &args).unwrap();
+        assert!(tree.prove_leaf(args.root, other_leaf, &proof, args.index).is_err());
+        // Leaf of the same nft in another tree
+        let (other_leaf, _) = get_compressed_nft_leaf(&Pubkey::new_unique(), &owner, &args).unwrap();
+        assert!(tree.prove_leaf(args.root, other_leaf, &proof, args.index).is_err());
+    }
+
+    #[test]
+    fn rejects_compressed_nfts_outside_the_gated_collection() {
+        let merkle_tree = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let gate_collection = Pubkey::new_unique();
+
+        // Wrong collection
+        let args = compressed_nft_args(Some(Collection { verified: true, key: collection }));
+        let (_, verified_collection) = get_compressed_nft_leaf(&merkle_tree, &owner, &args).unwrap();
+        assert_eq!(verified_collection, Some(collection));
+        assert!(!compressed_nft_gate_results(verified_collection, gate_collection)[0]);
+
+        // Unverified collection
+        let args = compressed_nft_args(Some(Collection { verified: false, key: gate_collection }));
+        let (_, verified_collection) = get_compressed_nft_leaf(&merkle_tree, &owner, &args).unwrap();
+        assert_eq!(verified_collection, None);
+        assert!(!compressed_nft_gate_results(verified_collection, gate_collection)[0]);
+
+        // No collection
+        let args = compressed_nft_args(None);
+        let (_, verified_collection) = get_compressed_nft_leaf(&merkle_tree, &owner, &args).unwrap();
+        assert_eq!(verified_collection, None);
+        assert!(!compressed_nft_gate_results(verified_collection, gate_collection)[0]);
+    }
+
+    #[test]
+    fn checks_compressed_nfts_per_gate() {
+        let collections = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        assert_eq!(
+            compressed_nft_gates_results(&[(0, collections[0]), (1, collections[1])], &collections),
+            vec![true, true],
+        );
+        assert_eq!(compressed_nft_gates_results(&[(1, collections[1])], &collections), vec![false, true]);
+        // Collections only count for the gate their cNFT is given for
+        assert_eq!(
+            compressed_nft_gates_results(&[(1, collections[0]), (0, collections[1])], &collections),
+            vec![false, false],
+        );
+    }
+
+    fn mint_with_extensions(extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0u8; TOKEN_2022_ACCOUNT_TYPE_INDEX];
+        data.push(TOKEN_2022_MINT_ACCOUNT_TYPE);
+
+        for (extension_type, value) in extensions {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+
+        data
+    }
+
+    #[test]
+    fn reads_mint_extensions() {
+        let pointer = [[1u8; 32], [2u8; 32]].concat();
+        let member = [&[3u8; 32][..], &[4u8; 32], &[0u8; 8]].concat();
+        let data = mint_with_extensions(&[
+            (GROUP_MEMBER_POINTER_EXTENSION, pointer.clone()),
+            (TOKEN_GROUP_MEMBER_EXTENSION, member.clone()),
+        ]);
+
+        assert_eq!(get_mint_extension(&data, GROUP_MEMBER_POINTER_EXTENSION), Some(pointer.as_slice()));
+        assert_eq!(get_mint_extension(&data, TOKEN_GROUP_MEMBER_EXTENSION), Some(member.as_slice()));
+        assert_eq!(get_mint_extension(&data, TOKEN_GROUP_MEMBER_EXTENSION + 1), None);
+    }
+
+    #[test]
+    fn ignores_invalid_mint_extensions() {
+        // Legacy mints have no extensions
+        assert_eq!(get_mint_extension(&[0u8; 82], GROUP_MEMBER_POINTER_EXTENSION), None);
+
+        // Token accounts share the layout but not the account type
+        let mut data = mint_with_extensions(&[(GROUP_MEMBER_POINTER_EXTENSION, vec![0u8; 64])]);
+        data[TOKEN_2022_ACCOUNT_TYPE_INDEX] = 2;
+        assert_eq!(get_mint_extension(&data, GROUP_MEMBER_POINTER_EXTENSION), None);
+
+        // Truncated values
+        let mut data = mint_with_extensions(&[(GROUP_MEMBER_POINTER_EXTENSION, vec![0u8; 64])]);
+        data.truncate(data.len() - 1);
+        assert_eq!(get_mint_extension(&data, GROUP_MEMBER_POINTER_EXTENSION), None);
+    }
+
+    fn with_mint_info<T>(mint: &Pubkey, owner: &Pubkey, mut data: Vec<u8>, f: impl FnOnce(&AccountInfo) -> T) -> T {
+        let mut lamports = 0;
+        let mint_info = AccountInfo::new(mint, false, false, &mut lamports, &mut data, owner, false, 0);
+        f(&mint_info)
+    }
+
+    #[test]
+    fn verifies_group_members() {
+        let mint = Pubkey::new_unique();
+        let group = Pubkey::new_unique();
+        let member = |member_address: &Pubkey, member_mint: &Pubkey, member_group: &Pubkey| mint_with_extensions(&[
+            (GROUP_MEMBER_POINTER_EXTENSION, [Pubkey::new_unique().to_bytes(), member_address.to_bytes()].concat()),
+            (TOKEN_GROUP_MEMBER_EXTENSION, [&member_mint.to_bytes()[..], &member_group.to_bytes(), &1u64.to_le_bytes()].concat()),
+        ]);
+        let is_member = |data, owner| with_mint_info(&mint, owner, data, |mint_info| is_valid_group_member(&group, mint_info));
+
+        assert!(is_member(member(&mint, &mint, &group), &token_2022::ID));
+        // Member of another group
+        assert!(!is_member(member(&mint, &mint, &Pubkey::new_unique()), &token_2022::ID));
+        // Member extension of another mint
+        assert!(!is_member(member(&mint, &Pubkey::new_unique(), &group), &token_2022::ID));
+        // Pointer to a member account other than the mint
+        assert!(!is_member(member(&Pubkey::new_unique(), &mint, &group), &token_2022::ID));
+        // No member extensions
+        assert!(!is_member(mint_with_extensions(&[]), &token_2022::ID));
+        // Mints of the legacy token program have no extensions
+        assert!(!is_member(member(&mint, &mint, &group), &anchor_spl::token::ID));
+    }
+
+    fn hash_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        if a <= b {
+            keccak::hashv(&[&a, &b]).to_bytes()
+        } else {
+            keccak::hashv(&[&b, &a]).to_bytes()
+        }
+    }
+
+    #[test]
+    fn verifies_allowlist_proofs() {
+        let authors = (0..4).map(|_| Pubkey::new_unique()).collect::<Vec<Pubkey>>();
+        let leaves = authors.iter()
+            .map(|author| keccak::hashv(&[author.as_ref()]).to_bytes())
+            .collect::<Vec<[u8; 32]>>();
+        let left = hash_pair(leaves[0], leaves[1]);
+        let right = hash_pair(leaves[2], leaves[3]);
+        let root = hash_pair(left, right);
+
+        assert!(is_valid_allowlist_proof(&root, &authors[0], &[leaves[1], right]));
+        assert!(is_valid_allowlist_proof(&root, &authors[1], &[leaves[0], right]));
+        assert!(is_valid_allowlist_proof(&root, &authors[2], &[leaves[3], left]));
+        assert!(is_valid_allowlist_proof(&root, &authors[3], &[leaves[2], left]));
+
+        // Proof for a different author
+        assert!(!is_valid_allowlist_proof(&root, &authors[0], &[leaves[0], right]));
+        // Unknown author
+        assert!(!is_valid_allowlist_proof(&root, &Pubkey::new_unique(), &[leaves[1], right]));
+        // Incomplete proof
+        assert!(!is_valid_allowlist_proof(&root, &authors[0], &[leaves[1]]));
+        // Single entry allowlist
+        assert!(is_valid_allowlist_proof(&leaves[0], &authors[0], &[]));
+    }
+
+    #[test]
+    fn checks_allowlist_proofs_per_gate() {
+        let author = Pubkey::new_unique();
+        let leaf = keccak::hashv(&[author.as_ref()]).to_bytes();
+        let siblings = [keccak::hashv(&[b"first"]).to_bytes(), keccak::hashv(&[b"second"]).to_bytes()];
+
+        // Both allowlists are required and include the author
+        let mut config = test_forum_config();
+        config.gate = siblings.iter().map(|sibling| {
+            let mut allowlist_gate = gate(Operator::And);
+            allowlist_gate.rule_type = Rule::Allowlist;
+            allowlist_gate.address = vec![Pubkey::new_from_array(hash_pair(leaf, *sibling))];
+            allowlist_gate
+        }).collect();
+
+        let gate_results = |allowlist_proofs: &[AllowlistProof]| get_gate_results(
+            &config,
+            &author,
+            &GateProofs {
+                credentials: &[],
+                credential_accounts: &[],
+                compressed_nft_collections: &[],
+                additional_signer: None,
+                allowlist_proofs,
+                permit_signers: &[],
+            },
+        ).unwrap();
+        let allowlist_proof = |gate: u8, sibling: [u8; 32]| AllowlistProof { gate, proof: vec![sibling] };
+
+        assert_eq!(
+            gate_results(&[allowlist_proof(0, siblings[0]), allowlist_proof(1, siblings[1])]),
+            vec![true, true],
         );
-        let new_leaf = Node::default();
+        assert_eq!(gate_results(&[allowlist_proof(1, siblings[1])]), vec![false, true]);
+        // Proofs only count for the gate they are given for
+        assert_eq!(
+            gate_results(&[allowlist_proof(1, siblings[0]), allowlist_proof(0, siblings[1])]),
+            vec![false, false],
+        );
+    }
 
-        replace_leaf(
-            &ctx.accounts.merkle_tree.key(),
-            *ctx.bumps.get("forum_config").unwrap(),
-            &ctx.accounts.compression_program.to_account_info(),
-            &ctx.accounts.forum_config.to_account_info(),
-            &ctx.accounts.merkle_tree.to_account_info(),
-            &ctx.accounts.log_wrapper.to_account_info(),
-            ctx.remaining_accounts,
-            root,
-            previous_leaf.to_node(),
-            new_leaf,
-            index,
-        )
+    fn ed25519_instruction_data(public_key: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let public_key_offset: u16 = 2 + 14;
+        let signature_offset: u16 = public_key_offset + 32;
+        let message_offset: u16 = signature_offset + 64;
+        let mut data = vec![1, 0];
+
+        for field in [
+            signature_offset,
+            instruction_index,
+            public_key_offset,
+            instruction_index,
+            message_offset,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        data
     }
-}
 
-pub fn append_leaf<'info>(
-    seed: &Pubkey,
-    bump: u8,
-    compression_program: &AccountInfo<'info>,
-    authority: &AccountInfo<'info>,
-    merkle_tree: &AccountInfo<'info>,
-    log_wrapper: &AccountInfo<'info>,
-    leaf_node: Node,
-) -> Result<()> {
-    let seeds = &[seed.as_ref(), &[bump]];
-    let authority_pda_signer = &[&seeds[..]];
-    let cpi_ctx = CpiContext::new_with_signer(
-        compression_program.clone(),
-        spl_account_compression::cpi::accounts::Modify {
-            authority: authority.clone(),
-            merkle_tree: merkle_tree.clone(),
-            noop: log_wrapper.clone(),
-        },
-        authority_pda_signer,
-    );
-    spl_account_compression::cpi::append(cpi_ctx, leaf_node)
-}
+    #[test]
+    fn parses_ed25519_permits() {
+        let signer = Pubkey::new_unique();
+        let permit = PostingPermit {
+            forum: Pubkey::new_unique(),
+            author: Pubkey::new_unique(),
+            expiry: 100,
+            nonce: 7,
+        };
+        let message = permit.try_to_vec().unwrap();
+        let data = ed25519_instruction_data(&signer, &message, u16::MAX);
+        let signatures = get_ed25519_signatures(&data);
 
-pub fn replace_leaf<'info>(
-    seed: &Pubkey,
-    bump: u8,
-    compression_program: &AccountInfo<'info>,
-    authority: &AccountInfo<'info>,
-    merkle_tree: &AccountInfo<'info>,
-    log_wrapper: &AccountInfo<'info>,
-    remaining_accounts: &[AccountInfo<'info>],
-    root_node: Node,
-    previous_leaf: Node,
-    new_leaf: Node,
-    index: u32,
-) -> Result<()> {
-    let seeds = &[seed.as_ref(), &[bump]];
-    let authority_pda_signer = &[&seeds[..]];
-    let cpi_ctx = CpiContext::new_with_signer(
-        compression_program.clone(),
-        spl_account_compression::cpi::accounts::Modify {
-            authority: authority.clone(),
-            merkle_tree: merkle_tree.clone(),
-            noop: log_wrapper.clone(),
-        },
-        authority_pda_signer,
-    )
-    .with_remaining_accounts(remaining_accounts.to_vec());
-    spl_account_compression::cpi::replace_leaf(cpi_ctx, root_node, previous_leaf, new_leaf, index)
-}
+        assert_eq!(signatures.len(), 1);
+        assert_eq!(signatures[0].0, signer);
+        assert_eq!(PostingPermit::try_from_slice(signatures[0].1).unwrap().nonce, 7);
 
-pub fn get_entry_id(tree_id: &Pubkey, nonce: u64) -> Pubkey {
-    Pubkey::find_program_address(
-        &[
-            ENTRY_PREFIX.as_ref(),
-            tree_id.as_ref(),
-            &nonce.to_le_bytes(),
-        ],
-        &crate::id(),
-    )
-    .0
-}
+        // Offsets into other instructions are ignored
+        assert!(get_ed25519_signatures(&ed25519_instruction_data(&signer, &message, 0)).is_empty());
+        // Truncated data
+        assert!(get_ed25519_signatures(&data[..data.len() - 1]).is_empty());
+        assert!(get_ed25519_signatures(&data[..10]).is_empty());
+        assert!(get_ed25519_signatures(&[]).is_empty());
+    }
 
-pub fn user_owns_nft<'info>(
-    user: &Pubkey,
-    mint: &Pubkey,
-    metadata: &Pubkey,
-    token_account: &Account<'info, TokenAccount>,
-) -> bool {
-    let (metadata_pda, _) = find_metadata_account(
-        &mint
-      );
-    
-    if metadata_pda != metadata.key() {
-        return false;
+    #[test]
+    fn rejects_used_permits() {
+        let permit = PostingPermit {
+            forum: Pubkey::new_unique(),
+            author: Pubkey::new_unique(),
+            expiry: 100,
+            nonce: 7,
+        };
+
+        assert!(permit.is_valid(&permit.forum, &permit.author, 0));
+        assert!(permit.is_valid(&permit.forum, &permit.author, 99));
+        assert!(!permit.is_valid(&permit.forum, &permit.author, 100));
+        assert!(!permit.is_valid(&permit.forum, &Pubkey::new_unique(), 0));
+        assert!(!permit.is_valid(&Pubkey::new_unique(), &permit.author, 0));
+
+        let signer = Pubkey::new_unique();
+        let permits = [(signer, permit.nonce)];
+        let (permit_nonce_pda, _) = Pubkey::find_program_address(
+            &[PermitNonce::PREFIX.as_bytes(), permit.forum.as_ref(), signer.as_ref(), &permit.nonce.to_le_bytes()],
+            &crate::id(),
+        );
+        let consume = |permit_nonce: &Pubkey, data: Vec<u8>| {
+            with_mint_info(permit_nonce, &crate::id(), data, |permit_nonce| {
+                consume_permit(permit_nonce, permit_nonce, permit_nonce, &permit.forum, &permits)
+            })
+        };
+
+        // Replaying a permit finds its nonce already recorded
+        assert_eq!(
+            consume(&permit_nonce_pda, vec![0; PermitNonce::SIZE]).unwrap_err(),
+            OndaSocialError::PermitUsed.into(),
+        );
+        // The nonce account of another permit
+        assert_eq!(
+            consume(&Pubkey::new_unique(), vec![]).unwrap_err(),
+            OndaSocialError::InvalidPermitNonce.into(),
+        );
     }
 
-    if token_account.mint != mint.key() {
-        return false;
+    #[test]
+    fn bans_expire() {
+        let mut ban = Ban {
+            merkle_tree: Pubkey::new_unique(),
+            author: Pubkey::new_unique(),
+            until: None,
+            reason: 0,
+        };
+
+        assert!(ban.is_active(i64::MAX));
+
+        ban.until = Some(100);
+        assert!(ban.is_active(99));
+        assert!(!ban.is_active(100));
+        assert!(!ban.is_active(101));
+        assert_eq!(Ban::SIZE, 8 + ban.try_to_vec().unwrap().len());
     }
 
-    if token_account.amount != 1 {
-        return false;
-    } 
+    #[test]
+    fn orders_pinned_entries() {
+        let entries = (0..ForumPins::MAX_PINS + 1).map(|_| Pubkey::new_unique()).collect::<Vec<Pubkey>>();
+        let mut forum_pins = ForumPins {
+            merkle_tree: Pubkey::new_unique(),
+            pins: vec![],
+        };
+        let pinned = |forum_pins: &ForumPins| forum_pins.pins.iter().map(|pin| pin.entry).collect::<Vec<Pubkey>>();
 
-    if token_account.owner != user.key() {
-        return false;
+        assert_eq!(forum_pins.pin(entries[0], 0, None, 0).unwrap(), 0);
+        assert_eq!(forum_pins.pin(entries[1], 0, Some(100), 0).unwrap(), 0);
+        // Out of range positions pin last
+        assert_eq!(forum_pins.pin(entries[2], u8::MAX, None, 0).unwrap(), 2);
+        assert_eq!(pinned(&forum_pins), vec![entries[1], entries[0], entries[2]]);
+
+        // Pinning an entry again moves it
+        assert_eq!(forum_pins.pin(entries[2], 1, None, 0).unwrap(), 1);
+        assert_eq!(pinned(&forum_pins), vec![entries[1], entries[2], entries[0]]);
+
+        forum_pins.pin(entries[3], 3, None, 0).unwrap();
+        forum_pins.pin(entries[4], 4, None, 0).unwrap();
+        assert_eq!(
+            forum_pins.pin(entries[5], 0, None, 0).unwrap_err(),
+            OndaSocialError::TooManyPins.into(),
+        );
+        // Expired pins make room
+        assert_eq!(forum_pins.pin(entries[5], 0, None, 100).unwrap(), 0);
+        assert_eq!(forum_pins.pins.len(), ForumPins::MAX_PINS);
+        assert!(!pinned(&forum_pins).contains(&entries[1]));
+
+        forum_pins.unpin(&entries[5]).unwrap();
+        assert_eq!(pinned(&forum_pins), vec![entries[2], entries[0], entries[3], entries[4]]);
+        assert_eq!(
+            forum_pins.unpin(&entries[5]).unwrap_err(),
+            OndaSocialError::PinNotFound.into(),
+        );
+
+        forum_pins.pin(entries[5], 0, Some(i64::MAX), 0).unwrap();
+        assert_eq!(ForumPins::SIZE, 8 + forum_pins.try_to_vec().unwrap().len());
     }
 
-    true
-}
+    #[test]
+    fn evaluates_flair_policies() {
+        let mut config = test_forum_config();
+        config.flair = vec!["alpha".to_string(), "meme".to_string()];
+        config.gate = vec![gate(Operator::And), gate(Operator::And)];
+        config.policy = vec![PolicyNode::Gate { index: 0 }];
+        let flair_policies = vec![FlairPolicy {
+            flair: "alpha".to_string(),
+            policy: vec![PolicyNode::Gate { index: 1 }],
+        }];
 
-pub fn is_valid_url(input: &str) -> bool {
-    match Url::parse(input) {
-        Ok(_) => true,  // The URL is valid.
-        Err(_) => false, // The URL is not valid.
+        assert!(validate_flair_policies(&config, &flair_policies).is_ok());
+        config.flair_policies = flair_policies.clone();
+
+        // Flair without a policy is only subject to the forum's policy
+        assert!(assert_flair_access(&config, None, &[true, false]).is_ok());
+        assert!(assert_flair_access(&config, Some("meme"), &[true, false]).is_ok());
+        assert!(assert_flair_access(&config, Some("alpha"), &[true, true]).is_ok());
+        assert_eq!(
+            assert_flair_access(&config, Some("alpha"), &[true, false]).unwrap_err(),
+            OndaSocialError::FlairUnauthorized.into(),
+        );
+
+        // Unknown flair
+        let mut invalid = flair_policies.clone();
+        invalid[0].flair = "beta".to_string();
+        assert!(validate_flair_policies(&config, &invalid).is_err());
+        // Duplicate flair
+        let invalid = [flair_policies.clone(), flair_policies.clone()].concat();
+        assert!(validate_flair_policies(&config, &invalid).is_err());
+        // Unknown gate
+        let mut invalid = flair_policies.clone();
+        invalid[0].policy = vec![PolicyNode::Gate { index: 2 }];
+        assert!(validate_flair_policies(&config, &invalid).is_err());
+        // Empty policy
+        let mut invalid = flair_policies;
+        invalid[0].policy = vec![];
+        assert!(validate_flair_policies(&config, &invalid).is_err());
     }
-}
 
-pub fn is_valid_token(
-    address: &Pubkey,
-    owner: &Pubkey,
-    mint: &Account<Mint>,
-    token_account: &Account<TokenAccount>,
-    amount: u64,
-) -> bool {
-    if mint.key().eq(&address) == false {
-        return false;
+    #[test]
+    fn restricts_announcement_flair() {
+        let mut config = test_forum_config();
+        config.flair = vec!["news".to_string(), "meme".to_string()];
+        config.announcement_flair = vec!["news".to_string()];
+        let news = "news".to_string();
+        let meme = "meme".to_string();
+
+        let admin = config.admin;
+        let other = Pubkey::new_unique();
+
+        assert!(assert_flair_allowed(&config, None, None).is_ok());
+        assert!(assert_flair_allowed(&config, Some(&meme), None).is_ok());
+        assert!(assert_flair_allowed(&config, Some(&news), Some(&admin)).is_ok());
+        assert_eq!(
+            assert_flair_allowed(&config, Some(&news), None).unwrap_err(),
+            OndaSocialError::AnnouncementFlair.into(),
+        );
+        assert!(assert_flair_allowed(&config, Some(&news), Some(&other)).is_err());
     }
-    if token_account.mint.eq(&mint.key()) == false {
-        return false;
+
+    #[test]
+    fn thread_locks_expire() {
+        let mut thread_lock = ThreadLock {
+            merkle_tree: Pubkey::new_unique(),
+            post: Pubkey::new_unique(),
+            until: None,
+        };
+
+        assert!(thread_lock.is_active(i64::MAX));
+
+        thread_lock.until = Some(100);
+        assert!(thread_lock.is_active(99));
+        assert!(!thread_lock.is_active(100));
+        assert_eq!(ThreadLock::SIZE, 8 + thread_lock.try_to_vec().unwrap().len());
     }
-    if token_account.owner.eq(&owner) == false {
-        return false;
+
+    #[test]
+    fn enforces_slow_mode() {
+        let slow_mode = SlowMode {
+            min_post_interval: 60,
+            min_comment_interval: 10,
+            max_entries_per_window: 3,
+        };
+        let mut activity = AuthorActivity {
+            merkle_tree: Pubkey::new_unique(),
+            author: Pubkey::new_unique(),
+            last_post_at: 0,
+            last_comment_at: 0,
+            window_start: 0,
+            window_count: 0,
+        };
+        let now = 1_700_000_000;
+
+        assert!(activity.can_post(&slow_mode, false, now));
+        activity.record_entry(false, now);
+        assert!(!activity.can_post(&slow_mode, false, now + 59));
+        assert!(activity.can_post(&slow_mode, false, now + 60));
+        // Posts and comments are limited separately
+        assert!(activity.can_post(&slow_mode, true, now));
+        activity.record_entry(true, now + 5);
+        assert!(!activity.can_post(&slow_mode, true, now + 14));
+        assert!(activity.can_post(&slow_mode, true, now + 15));
+        assert_eq!(activity.window_count, 2);
+
+        // At most `max_entries_per_window` entries per window
+        activity.record_entry(false, now + 60);
+        assert!(!activity.can_post(&slow_mode, false, now + 120));
+        assert!(!activity.can_post(&slow_mode, true, now + 120));
+        assert!(activity.can_post(&slow_mode, false, now + AuthorActivity::WINDOW));
+
+        // The rolling count restarts once the window has passed
+        activity.record_entry(false, now + AuthorActivity::WINDOW);
+        assert_eq!(activity.window_start, now + AuthorActivity::WINDOW);
+        assert_eq!(activity.window_count, 1);
+        assert_eq!(AuthorActivity::SIZE, 8 + activity.try_to_vec().unwrap().len());
     }
-    if token_account.amount.ge(&1) == false {
-        return false;
+
+    #[test]
+    fn rolls_over_to_attached_trees() {
+        let first_tree = Pubkey::new_unique();
+        let second_tree = Pubkey::new_unique();
+        let mut config = test_forum_config();
+        config.trees = vec![ForumTree { merkle_tree: first_tree, capacity: 8, shard: false }];
+
+        assert_eq!(config.get_active_tree().unwrap().merkle_tree, first_tree);
+
+        config.post_count = 8;
+        assert!(!config.contains_post_capacity(1));
+        assert!(config.get_active_tree().is_none());
+
+        config.attach_tree(second_tree, 16);
+        assert_eq!(config.total_capacity, 24);
+        assert!(config.contains_post_capacity(16));
+        assert!(config.contains_tree(&first_tree));
+        assert!(config.contains_tree(&second_tree));
+        assert_eq!(config.get_active_tree().unwrap().merkle_tree, second_tree);
+
+        config.post_count = 7;
+        assert_eq!(config.get_active_tree().unwrap().merkle_tree, first_tree);
+
+        config.post_count = 24;
+        assert!(config.get_active_tree().is_none());
+
+        // Shards are written to directly and never become the active tree
+        let shard_tree = Pubkey::new_unique();
+        config.add_shard(shard_tree, 16);
+        assert_eq!(config.total_capacity, 24);
+        assert!(config.contains_tree(&shard_tree));
+        assert!(config.get_active_tree().is_none());
     }
-    if amount < token_account.amount {
-        return false;
+
+    #[test]
+    fn enforces_content_limits() {
+        let mut config = test_forum_config();
+        let post = |title: &str, nsfw: bool| DataV1::TextPost {
+            title: title.to_string(),
+            uri: "https://example.com".to_string(),
+            flair: None,
+            nsfw,
+            spoiler: false,
+        };
+        let video = DataV1::VideoPost {
+            title: "video".to_string(),
+            uri: "https://example.com".to_string(),
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+
+        assert!(validate_data(&config, &post("title", true)).is_ok());
+        assert!(validate_data(&config, &video).is_ok());
+
+        config.content_limits = ContentLimits {
+            max_title_len: 5,
+            max_uri_len: 19,
+            max_flair_len: 8,
+            max_body_len: 0,
+            entry_types: ContentLimits::TEXT_POST | ContentLimits::COMMENT,
+            allow_nsfw: false,
+        };
+
+        assert!(validate_data(&config, &post("title", false)).is_ok());
+        assert_eq!(
+            validate_data(&config, &post("titles", false)).unwrap_err(),
+            OndaSocialError::TitleTooLong.into(),
+        );
+        assert_eq!(
+            validate_data(&config, &post("title", true)).unwrap_err(),
+            OndaSocialError::NsfwNotAllowed.into(),
+        );
+        assert_eq!(
+            validate_data(&config, &video).unwrap_err(),
+            OndaSocialError::EntryTypeDisabled.into(),
+        );
+        assert_eq!(
+            validate_data(&config, &DataV1::Comment {
+                post: Pubkey::new_unique(),
+                parent: None,
+                uri: "https://example.com/a".to_string(),
+            }).unwrap_err(),
+            OndaSocialError::InvalidUri.into(),
+        );
     }
-    true
-} 
 
-pub fn is_valid_nft(
-    address: &Pubkey,
-    mint: &Account<Mint>,
-    metadata_info: &AccountInfo,
-) -> bool {
-    let metadata = Metadata::deserialize(
-        &mut metadata_info.data.borrow_mut().as_ref()
-    ).unwrap();
+    #[test]
+    fn validates_content_limits() {
+        let mut config = test_forum_config();
+        config.flair = vec!["short".to_string(), "much longer".to_string()];
+        let limits = ContentLimits::default();
+        let longest_flair = "much longer".len() as u16;
 
-    // Check the metadata address is valid pda for this mint
-    let (metadata_pda, _) = find_metadata_account(
-        &mint.key()
-    );
+        assert!(validate_content_limits(&config, &limits).is_ok());
+        assert_eq!(
+            validate_content_limits(&config, &ContentLimits { entry_types: 0, ..limits.clone() }).unwrap_err(),
+            OndaSocialError::EntryTypeDisabled.into(),
+        );
+        assert_eq!(
+            validate_content_limits(&config, &ContentLimits { entry_types: 1 << 7, ..limits.clone() }).unwrap_err(),
+            OndaSocialError::InvalidEntryTypes.into(),
+        );
+        assert_eq!(
+            validate_content_limits(&config, &ContentLimits { max_title_len: 0, ..limits.clone() }).unwrap_err(),
+            OndaSocialError::InvalidTitleLimit.into(),
+        );
+        assert_eq!(
+            validate_content_limits(&config, &ContentLimits { max_uri_len: 0, ..limits.clone() }).unwrap_err(),
+            OndaSocialError::InvalidUriLimit.into(),
+        );
+        assert!(validate_content_limits(&config, &ContentLimits { max_flair_len: longest_flair, ..limits.clone() }).is_ok());
+        assert_eq!(
+            validate_content_limits(&config, &ContentLimits { max_flair_len: longest_flair - 1, ..limits }).unwrap_err(),
+            OndaSocialError::InvalidFlairLimit.into(),
+        );
+    }
 
-    if metadata_pda.eq(&metadata_info.key()) == false {
-        return false
+    #[test]
+    fn enforces_uri_rules() {
+        let mut config = test_forum_config();
+        let image = |uri: &str| DataV1::ImagePost {
+            title: "image".to_string(),
+            uri: uri.to_string(),
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+        let link = |uri: &str| DataV1::LinkPost {
+            title: "link".to_string(),
+            uri: uri.to_string(),
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+
+        assert!(validate_data(&config, &link("javascript:alert(1)")).is_ok());
+
+        config.uri_rules = vec![
+            UriRule {
+                entry_types: ContentLimits::IMAGE_POST,
+                schemes: vec!["https".to_string(), "ar".to_string(), "ipfs".to_string()],
+                hosts: vec!["arweave.net".to_string()],
+            },
+            UriRule {
+                entry_types: ContentLimits::LINK_POST | ContentLimits::COMMENT,
+                schemes: vec!["https".to_string()],
+                hosts: vec![],
+            },
+        ];
+        assert!(validate_uri_rules(&config.uri_rules).is_ok());
+
+        assert!(validate_data(&config, &image("https://Arweave.net/abc")).is_ok());
+        assert_eq!(
+            validate_data(&config, &image("https://example.com/abc")).unwrap_err(),
+            OndaSocialError::UriHostNotAllowed.into(),
+        );
+        assert_eq!(
+            validate_data(&config, &image("data:image/png;base64,AAAA")).unwrap_err(),
+            OndaSocialError::UriSchemeNotAllowed.into(),
+        );
+        assert!(validate_data(&config, &link("https://example.com")).is_ok());
+        assert_eq!(
+            validate_data(&config, &link("javascript:alert(1)")).unwrap_err(),
+            OndaSocialError::UriSchemeNotAllowed.into(),
+        );
+
+        // Entry types without a rule accept any valid uri
+        let text = DataV1::TextPost {
+            title: "text".to_string(),
+            uri: "ar://abc".to_string(),
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+        assert!(validate_data(&config, &text).is_ok());
+
+        config.uri_rules.push(UriRule {
+            entry_types: ContentLimits::TEXT_POST | ContentLimits::IMAGE_POST,
+            schemes: vec![],
+            hosts: vec![],
+        });
+        assert!(validate_uri_rules(&config.uri_rules).is_err());
+        assert!(validate_uri_rules(&[UriRule { entry_types: 0, schemes: vec![], hosts: vec![] }]).is_err());
     }
 
-    // Check the metadata is verified for this collection
-    let is_valid_collection = match metadata.collection {
-        Some(collection) => collection.verified == true && collection.key.eq(&address),
-        None => false,
-    };
+    #[test]
+    fn verifies_entry_content() {
+        let body = b"# Hello\n\nworld";
+        let content = ContentDigest {
+            uri: "https://arweave.net/abc".to_string(),
+            mime_type: "text/markdown".to_string(),
+            algorithm: DigestAlgorithm::Sha256,
+            digest: ContentDigest::hash_content(&DigestAlgorithm::Sha256, body),
+        };
+        let data = DataV2::TextPost {
+            title: "hello".to_string(),
+            content: content.clone(),
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+        let leaf = LeafSchema::new_v0(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            None,
+            0,
+            keccak::hashv(&[&data.try_to_vec().unwrap()]).to_bytes(),
+        );
 
-    is_valid_collection
-}
+        assert!(verify_entry_content(&leaf, &data, body).is_ok());
+        assert_eq!(
+            verify_entry_content(&leaf, &data, b"# Hello\n\nchanged").unwrap_err(),
+            OndaSocialError::InvalidContentDigest.into(),
+        );
 
-pub fn validate_flair(config: &ForumConfig, flair: &Option<String>) -> Result<bool> {
-    if flair.is_none() {
-        return Ok(true);
+        let tampered = DataV2::TextPost {
+            title: "hello".to_string(),
+            content: ContentDigest {
+                algorithm: DigestAlgorithm::Keccak256,
+                digest: ContentDigest::hash_content(&DigestAlgorithm::Keccak256, b"other"),
+                ..content
+            },
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+        assert_eq!(
+            verify_entry_content(&leaf, &tampered, b"other").unwrap_err(),
+            OndaSocialError::InvalidDataHash.into(),
+        );
     }
 
-    let flair = flair.clone().unwrap();
-    require!(flair.len() <= MAX_FLAIR_LEN, OndaSocialError::FlairTooLong);
-    require!(config.flair.iter().find(|name| **name == flair).is_some(), OndaSocialError::InvalidFlair);
-    Ok(true)
-}
+    #[test]
+    fn validates_inline_bodies() {
+        let mut config = test_forum_config();
+        let comment = |body: &str| DataV2::ShortComment {
+            post: Pubkey::new_unique(),
+            parent: None,
+            depth: 1,
+            body: body.to_string(),
+        };
 
-pub fn validate_post_schema(title: &str, uri: &str) -> Result<bool> {
-    require!(is_valid_url(&uri), OndaSocialError::InvalidUri);
-    require_gte!(MAX_URI_LEN, uri.len(), OndaSocialError::InvalidUri);
-    require_gte!(MAX_TITLE_LEN, title.len(), OndaSocialError::TitleTooLong);
-    Ok(true)
-}
+        assert!(validate_data_v2(&config, &comment("gm")).is_ok());
+        assert!(validate_data_v2(&config, &comment(&"a".repeat(MAX_BODY_LEN))).is_ok());
+        assert_eq!(
+            validate_data_v2(&config, &comment(&"a".repeat(MAX_BODY_LEN + 1))).unwrap_err(),
+            OndaSocialError::BodyTooLong.into(),
+        );
+        assert_eq!(
+            validate_data_v2(&config, &comment("")).unwrap_err(),
+            OndaSocialError::InvalidBody.into(),
+        );
 
-pub fn evaluate_operations(operations: Vec<OperationResult>) -> bool {
-    let mut overall_result = false;
-    let mut or_case_result = false;
+        let post = DataV2::ShortPost {
+            title: "gm".to_string(),
+            body: "gm".to_string(),
+            flair: None,
+            nsfw: false,
+            spoiler: false,
+        };
+        assert!(validate_data_v2(&config, &post).is_ok());
 
-    if operations.len() == 0 {
-        return true;
+        config.content_limits.entry_types = ContentLimits::COMMENT;
+        assert_eq!(
+            validate_data_v2(&config, &post).unwrap_err(),
+            OndaSocialError::EntryTypeDisabled.into(),
+        );
+
+        // Inline entries have no off-chain content to verify
+        let data = comment("gm");
+        let leaf = LeafSchema::new_v0(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            0,
+            None,
+            0,
+            keccak::hashv(&[&data.try_to_vec().unwrap()]).to_bytes(),
+        );
+        assert_eq!(
+            verify_entry_content(&leaf, &data, b"gm").unwrap_err(),
+            OndaSocialError::InvalidContentDigest.into(),
+        );
     }
-    
-    for op in operations {
-        match op.operator {
-            Operator::And => {
-                overall_result &= op.result;
-                or_case_result &= op.result;
-            }
-            Operator::Or => {
-                if op.result {
-                    or_case_result = true;
-                }
-                overall_result |= or_case_result;
+
+    #[test]
+    fn logs_inline_bodies_separately() {
+        let leaf = LeafSchema::new_v0(Pubkey::new_unique(), Pubkey::new_unique(), 0, None, 0, [0; 32]);
+        let event = leaf.to_event().try_to_vec().unwrap();
+
+        // Indexers decode leaf events as the event type and version followed by the schema and leaf hash
+        assert_eq!(event.len(), 2 + leaf.try_to_vec().unwrap().len() + 32);
+        assert_eq!(event[0], OndaSocialEventType::LeafSchemaEvent as u8);
+
+        let body = EntryBodyEvent::new(leaf.id(), "gm".to_string()).try_to_vec().unwrap();
+        assert_eq!(body[0], OndaSocialEventType::EntryBodyEvent as u8);
+        assert_eq!(&body[2..34], leaf.id().as_ref());
+    }
+
+    #[test]
+    fn validates_polls() {
+        let options = |count: usize| (0..count).map(|i| format!("option {}", i)).collect::<Vec<String>>();
+
+        assert!(validate_poll_options(&options(2)).is_ok());
+        assert!(validate_poll_options(&options(MAX_POLL_OPTIONS)).is_ok());
+        assert!(validate_poll_options(&options(1)).is_err());
+        assert!(validate_poll_options(&options(MAX_POLL_OPTIONS + 1)).is_err());
+        assert!(validate_poll_options(&["yes".to_string(), "".to_string()]).is_err());
+
+        let mut tally = PollTally {
+            merkle_tree: Pubkey::new_unique(),
+            poll: Pubkey::new_unique(),
+            votes: vec![],
+        };
+        assert_eq!(tally.record_vote(3, 2), 1);
+        assert_eq!(tally.record_vote(3, 2), 2);
+        assert_eq!(tally.record_vote(3, 0), 1);
+        assert_eq!(tally.votes, vec![1, 0, 2]);
+
+        tally.votes = vec![0; MAX_POLL_OPTIONS];
+        assert_eq!(PollTally::SIZE, 8 + tally.try_to_vec().unwrap().len());
+    }
+
+    #[test]
+    fn records_entry_votes() {
+        let mut vote = EntryVote {
+            merkle_tree: Pubkey::default(),
+            entry: Pubkey::default(),
+            voter: Pubkey::default(),
+            direction: VoteDirection::Up,
+            voted_at: 0,
+        };
+
+        assert_eq!(vote.record_vote(VoteDirection::Up, 1), Some(1));
+        vote.voter = Pubkey::new_unique();
+        assert_eq!(vote.record_vote(VoteDirection::Up, 2), None);
+        assert_eq!(vote.record_vote(VoteDirection::Down, 3), Some(-2));
+        assert_eq!(vote.record_vote(VoteDirection::Down, 4), None);
+        assert_eq!(vote.record_vote(VoteDirection::Up, 5), Some(2));
+        assert_eq!(vote.voted_at, 5);
+        assert_eq!(EntryVote::SIZE, 8 + vote.try_to_vec().unwrap().len());
+    }
+
+    #[test]
+    fn rejects_edits_changing_entry_kind() {
+        let post = Pubkey::new_unique();
+        let comment = |post: Pubkey, parent: Option<Pubkey>, depth: u8| EntryData::V2 {
+            data: DataV2::ShortComment { post, parent, depth, body: "gm".to_string() },
+        };
+        let text_post = EntryData::V2 {
+            data: DataV2::ShortPost {
+                title: "test".to_string(),
+                body: "gm".to_string(),
+                flair: None,
+                nsfw: false,
+                spoiler: false,
             },
-            Operator::Not => {
-                if op.result == false {
-                    overall_result = false;
-                    break;
-                }
-            }
-        }
+        };
+        let v1_comment = EntryData::V1 {
+            data: DataV1::Comment { post, parent: None, uri: "https://example.com".to_string() },
+        };
+
+        assert!(assert_same_entry_kind(&comment(post, None, 1), &comment(post, None, 1)).is_ok());
+        assert!(assert_same_entry_kind(&v1_comment, &v1_comment.clone()).is_ok());
+        assert_eq!(
+            assert_same_entry_kind(&comment(post, None, 1), &comment(Pubkey::new_unique(), None, 1)).unwrap_err(),
+            OndaSocialError::EntryKindChanged.into(),
+        );
+        assert!(assert_same_entry_kind(&comment(post, None, 1), &comment(post, Some(post), 2)).is_err());
+        assert!(assert_same_entry_kind(&comment(post, None, 1), &text_post).is_err());
+        assert!(assert_same_entry_kind(&text_post, &comment(post, None, 1)).is_err());
+        // Unverified `DataV1` comments cannot become `DataV2` comments with a depth
+        assert!(assert_same_entry_kind(&v1_comment, &comment(post, None, 1)).is_err());
     }
-    
-    overall_result
-}
\ No newline at end of file
+
+    #[test]
+    fn validates_comment_depth() {
+        let mut config = test_forum_config();
+        let parent = Some(Pubkey::new_unique());
+
+        assert!(validate_comment_depth(&config, &None, 1).is_ok());
+        assert!(validate_comment_depth(&config, &parent, 2).is_ok());
+        assert!(validate_comment_depth(&config, &parent, 200).is_ok());
+        assert!(validate_comment_depth(&config, &None, 0).is_err());
+        assert!(validate_comment_depth(&config, &None, 2).is_err());
+        assert!(validate_comment_depth(&config, &parent, 1).is_err());
+
+        config.strict_comments = Some(StrictComments { max_depth: 3 });
+        assert!(validate_comment_depth(&config, &parent, 3).is_ok());
+        assert_eq!(
+            validate_comment_depth(&config, &parent, 4).unwrap_err(),
+            OndaSocialError::CommentTooDeep.into(),
+        );
+    }
+}
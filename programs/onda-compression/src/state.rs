@@ -1,10 +1,11 @@
-use anchor_lang::{prelude::*, solana_program::keccak};
+use anchor_lang::{prelude::*, solana_program::{hash, keccak}};
 use borsh::{BorshDeserialize, BorshSerialize};
 use spl_account_compression::Node;
+use crate::error::OndaSocialError;
 
 pub const ENTRY_PREFIX: &str = "entry";
-pub const BASE_FORUM_CONFIG_SIZE: usize = 8 + 8 + 8 + 32 + 4;
-pub const BASE_GATE_SIZE: usize = 8 + 1 + 1 + 4;
+pub const BASE_FORUM_CONFIG_SIZE: usize = 8 + 8 + 8 + 32 + 4 + 13 + 50 + 17 + ContentLimits::SIZE + 2;
+pub const BASE_GATE_SIZE: usize = 8 + 9 + 2 + 1 + 1 + 4;
 
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
 pub enum Rule {
@@ -12,11 +13,24 @@ pub enum Rule {
     Nft,
     CompressedNft,
     AdditionalSigner,
+    /// Each `Gate::address` is the merkle root of an allowlist of authors
+    Allowlist,
+    /// Requires a `PostingPermit` signed by a `Gate::address` key, verified by the ed25519 program
+    SignedPermit,
 }
 
-pub struct OperationResult {
-    pub operator: Operator,
-    pub result: bool,
+impl Rule {
+    /// Number of remaining accounts needed to prove a credential for this rule
+    pub fn credential_accounts_len(&self) -> usize {
+        match self {
+            Rule::Token => 2,
+            Rule::Nft => 3,
+            Rule::CompressedNft => 0,
+            Rule::AdditionalSigner => 0,
+            Rule::Allowlist => 0,
+            Rule::SignedPermit => 0,
+        }
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
@@ -28,30 +42,673 @@ pub enum Operator {
 
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Gate {
+    /// Minimum token balance, inclusive. Zero also admits empty token accounts of the mint.
+    pub min_amount: u64,
+    /// Maximum token balance, inclusive
+    pub max_amount: Option<u64>,
+    /// Decimal places of `min_amount` and `max_amount`. When `None` the bounds
+    /// are compared against the raw token account amount.
+    pub decimals: Option<u8>,
+    pub rule_type: Rule,
+    /// Only used when building a policy from a flat list of gates
+    pub operator: Operator,
+    pub address: Vec<Pubkey>,
+}
+
+impl Gate {
+    /// Migrates a gate which only allowed balances from one token up to `amount`. Forum admins
+    /// set `amount` as the balance required to post, so it becomes the minimum, raised to one
+    /// token so that a zero `amount` still requires holding the token. The migrated gate has no
+    /// maximum, admins relying on the old cap have to set `max_amount` again with `set_gates`.
+    pub fn from_v0(gate: GateV0) -> Self {
+        Self {
+            min_amount: gate.amount.max(1),
+            max_amount: None,
+            decimals: None,
+            rule_type: gate.rule_type,
+            operator: gate.operator,
+            address: gate.address,
+        }
+    }
+
+    /// Compares `amount / 10^mint_decimals` against the bounds scaled by `decimals`,
+    /// cross-multiplying so no precision is lost. Overflowing values never pass.
+    pub fn is_valid_balance(&self, amount: u64, mint_decimals: u8) -> bool {
+        let (amount_scale, bound_scale) = match self.decimals {
+            Some(decimals) => (
+                10u128.checked_pow(decimals as u32),
+                10u128.checked_pow(mint_decimals as u32),
+            ),
+            None => (Some(1), Some(1)),
+        };
+        let scale = |value: u64, scale: Option<u128>| scale.and_then(|scale| scale.checked_mul(value as u128));
+        let amount = match scale(amount, amount_scale) {
+            Some(amount) => amount,
+            None => return false,
+        };
+
+        let is_above_min = matches!(scale(self.min_amount, bound_scale), Some(min) if amount >= min);
+        let is_below_max = match self.max_amount {
+            Some(max) => matches!(scale(max, bound_scale), Some(max) if amount <= max),
+            None => true,
+        };
+
+        is_above_min && is_below_max
+    }
+}
+
+/// Layout of `Gate` before balance bounds were added, kept for migrating existing forums
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct GateV0 {
     pub amount: u64,
     pub rule_type: Rule,
     pub operator: Operator,
     pub address: Vec<Pubkey>,
 }
 
+/// A node of a forum's access policy. The policy is stored as a flat list where
+/// the first node is the root and children are referenced by their position,
+/// which must come after their parent.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub enum PolicyNode {
+    /// Passes when the gate at this index of `ForumConfig::gate` passes
+    Gate { index: u8 },
+    /// Passes when all children pass
+    And { children: Vec<u8> },
+    /// Passes when any child passes
+    Or { children: Vec<u8> },
+    /// Passes when the child fails
+    Not { child: u8 },
+}
+
+impl PolicyNode {
+    pub fn get_size(&self) -> usize {
+        match self {
+            PolicyNode::Gate { .. } => 1 + 1,
+            PolicyNode::And { children } | PolicyNode::Or { children } => 1 + 4 + children.len(),
+            PolicyNode::Not { .. } => 1 + 1,
+        }
+    }
+
+    /// Builds the policy equivalent to a flat list of gates: every `And` gate
+    /// must pass, every `Not` gate must fail and at least one `Or` gate must pass.
+    pub fn from_gates(gates: &[Gate]) -> Vec<PolicyNode> {
+        if gates.is_empty() {
+            return vec![];
+        }
+
+        let mut nodes = vec![PolicyNode::And { children: vec![] }];
+        let mut required = vec![];
+        let mut any_of = vec![];
+
+        for (index, gate) in gates.iter().enumerate() {
+            let index = index as u8;
+
+            match gate.operator {
+                Operator::And => {
+                    required.push(nodes.len() as u8);
+                    nodes.push(PolicyNode::Gate { index });
+                },
+                Operator::Not => {
+                    let position = nodes.len() as u8;
+                    required.push(position);
+                    nodes.push(PolicyNode::Not { child: position + 1 });
+                    nodes.push(PolicyNode::Gate { index });
+                },
+                Operator::Or => {
+                    any_of.push(index);
+                },
+            }
+        }
+
+        if !any_of.is_empty() {
+            let position = nodes.len() as u8;
+            let children = (1..=any_of.len() as u8).map(|offset| position + offset).collect();
+            required.push(position);
+            nodes.push(PolicyNode::Or { children });
+            nodes.extend(any_of.into_iter().map(|index| PolicyNode::Gate { index }));
+        }
+
+        nodes[0] = PolicyNode::And { children: required };
+        nodes
+    }
+}
+
+/// Maps the next credential accounts in `remaining_accounts` to the gate they are checked against
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Credential {
+    pub gate: u8,
+}
+
+/// Merkle proof that the author is in the allowlist of the `Rule::Allowlist` gate at index `gate`
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct AllowlistProof {
+    pub gate: u8,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Proof of ownership for a Bubblegum cNFT, checked against the `Rule::CompressedNft` gate at index
+/// `gate`. The cNFT's merkle tree followed by the `proof_len` proof nodes for the leaf are passed as
+/// remaining accounts after the credential accounts, in the order of the proofs.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct CompressedNftArgs {
+    pub gate: u8,
+    pub root: [u8; 32],
+    pub delegate: Pubkey,
+    pub nonce: u64,
+    pub index: u32,
+    /// Borsh serialized Bubblegum `MetadataArgs`, hashed into the leaf's data and creator hashes
+    pub metadata: Vec<u8>,
+    pub proof_len: u8,
+}
+
+/// Borsh serialized message signed off-chain for `Rule::SignedPermit` gates. Each permit can be
+/// used once before `expiry`, its nonce is recorded in a `PermitNonce` account when it is used.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct PostingPermit {
+    /// Merkle tree of the forum
+    pub forum: Pubkey,
+    pub author: Pubkey,
+    pub expiry: i64,
+    /// Must be unique per signer and forum, a used nonce can't be signed again
+    pub nonce: u64,
+}
+
+impl PostingPermit {
+    pub fn is_valid(&self, forum: &Pubkey, author: &Pubkey, now: i64) -> bool {
+        self.forum.eq(forum) && self.author.eq(author) && self.expiry > now
+    }
+}
+
+/// Minimum number of seconds between an author's entries, a zero interval disables the limit
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct SlowMode {
+    pub min_post_interval: u32,
+    pub min_comment_interval: u32,
+    /// Most entries an author may add per `AuthorActivity::WINDOW`, zero disables the limit
+    pub max_entries_per_window: u32,
+}
+
+/// Fee charged to the signer of `add_entry`, in lamports or in `mint` tokens when set
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct PostingFee {
+    pub mint: Option<Pubkey>,
+    pub post_amount: u64,
+    pub comment_amount: u64,
+}
+
+impl PostingFee {
+    pub fn get_amount(&self, is_comment: bool) -> u64 {
+        match is_comment {
+            true => self.comment_amount,
+            false => self.post_amount,
+        }
+    }
+}
+
+/// Lamports escrowed by the signer of `add_entry`, which the author can reclaim once
+/// `reclaim_window` seconds have passed without the entry being deleted. Deleting the entry
+/// within the window forfeits the bond to the treasury, even when the author deletes it.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct BondMode {
+    pub amount: u64,
+    pub reclaim_window: i64,
+}
+
+/// Per-forum limits on entry content
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ContentLimits {
+    pub max_title_len: u16,
+    pub max_uri_len: u16,
+    pub max_flair_len: u16,
+    /// Maximum length of an inline `DataV2` body, zero disables inline bodies
+    pub max_body_len: u16,
+    /// Bit set of the `DataV1` variants accepted by the forum, see `DataV1::entry_type`
+    pub entry_types: u8,
+    pub allow_nsfw: bool,
+}
+
+impl ContentLimits {
+    pub const SIZE: usize = 2 + 2 + 2 + 2 + 1 + 1;
+
+    pub const TEXT_POST: u8 = 1 << 0;
+    pub const IMAGE_POST: u8 = 1 << 1;
+    pub const LINK_POST: u8 = 1 << 2;
+    pub const VIDEO_POST: u8 = 1 << 3;
+    pub const COMMENT: u8 = 1 << 4;
+    pub const POLL_POST: u8 = 1 << 5;
+    pub const ALL_ENTRY_TYPES: u8 =
+        Self::TEXT_POST | Self::IMAGE_POST | Self::LINK_POST | Self::VIDEO_POST | Self::COMMENT | Self::POLL_POST;
+
+    pub fn is_enabled(&self, entry_type: u8) -> bool {
+        self.entry_types & entry_type != 0
+    }
+}
+
+impl Default for ContentLimits {
+    fn default() -> Self {
+        Self {
+            max_title_len: crate::MAX_TITLE_LEN as u16,
+            max_uri_len: crate::MAX_URI_LEN as u16,
+            max_flair_len: crate::MAX_FLAIR_LEN as u16,
+            max_body_len: crate::MAX_BODY_LEN as u16,
+            entry_types: Self::ALL_ENTRY_TYPES,
+            allow_nsfw: true,
+        }
+    }
+}
+
+/// Requires new comments to prove that their parent entry exists, see `add_entry_v2`.
+/// Top level comments have a depth of one.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct StrictComments {
+    pub max_depth: u8,
+}
+
+/// Restricts a flair to authors satisfying `policy`, which is evaluated against the forum's gates in
+/// addition to the forum's own policy. Gates only meant for flair should be left out of the forum's
+/// policy. Flair only the forum admin may use is announcement flair instead.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct FlairPolicy {
+    pub flair: String,
+    pub policy: Vec<PolicyNode>,
+}
+
+impl FlairPolicy {
+    pub fn get_size(&self) -> usize {
+        4 + self.flair.len() + 4 + self.policy.iter().fold(0, |acc, node| acc + node.get_size())
+    }
+}
+
+/// Restricts the uris of the entry types in `entry_types`. Schemes and hosts are compared
+/// case-insensitively and hosts must match exactly. An empty list allows any value.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct UriRule {
+    /// Bit set of entry types, see `ContentLimits::entry_types`
+    pub entry_types: u8,
+    pub schemes: Vec<String>,
+    pub hosts: Vec<String>,
+}
+
+impl UriRule {
+    pub fn get_size(&self) -> usize {
+        let list_size = |list: &Vec<String>| 4 + list.iter().fold(0, |acc, item| acc + 4 + item.len());
+        1 + list_size(&self.schemes) + list_size(&self.hosts)
+    }
+
+    pub fn applies_to(&self, entry_type: u8) -> bool {
+        self.entry_types & entry_type != 0
+    }
+
+    pub fn allows_scheme(&self, scheme: &str) -> bool {
+        self.schemes.is_empty() || self.schemes.iter().any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }
+
+    pub fn allows_host(&self, host: Option<&str>) -> bool {
+        if self.hosts.is_empty() {
+            return true;
+        }
+
+        match host {
+            Some(host) => self.hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)),
+            None => false,
+        }
+    }
+}
+
+/// A merkle tree owned by the forum. Trees are filled in order, except for shards which keep
+/// their own post count in a `ForumShard` account.
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ForumTree {
+    pub merkle_tree: Pubkey,
+    pub capacity: u64,
+    pub shard: bool,
+}
+
+impl ForumTree {
+    pub const SIZE: usize = 32 + 8 + 1;
+}
+
+/// Post counter for a shard tree, so that entries added to different shards do not
+/// write to the same accounts
+#[account]
+pub struct ForumShard {
+    pub merkle_tree: Pubkey,
+    pub tree: Pubkey,
+    pub total_capacity: u64,
+    pub post_count: u64,
+}
+
+impl ForumShard {
+    pub const PREFIX: &'static str = "shard";
+    pub const SIZE: usize = 8 + 32 + 32 + 8 + 8;
+
+    pub fn increment_post_count(&mut self) {
+        self.post_count = self.post_count.saturating_add(1);
+    }
+
+    pub fn contains_post_capacity(&self, requested_capacity: u64) -> bool {
+        let remaining_posts = self.total_capacity.saturating_sub(self.post_count);
+        requested_capacity <= remaining_posts
+    }
+}
+
 #[account]
 pub struct ForumConfig {
     pub total_capacity: u64,
     pub post_count: u64,
     pub admin: Pubkey,
     pub flair: Vec<String>,
-    pub gate: Vec<Gate>
+    /// Flair only usable by entries co-signed by the admin, a subset of `flair`
+    pub announcement_flair: Vec<String>,
+    /// At most one policy applies to each flair, flair without a policy can be used by anyone
+    pub flair_policies: Vec<FlairPolicy>,
+    pub gate: Vec<Gate>,
+    pub policy: Vec<PolicyNode>,
+    pub slow_mode: Option<SlowMode>,
+    pub posting_fee: Option<PostingFee>,
+    pub bond_mode: Option<BondMode>,
+    pub content_limits: ContentLimits,
+    pub strict_comments: Option<StrictComments>,
+    /// At most one rule applies to each entry type, entry types without a rule accept any valid uri
+    pub uri_rules: Vec<UriRule>,
+    /// Every tree owned by the forum, starting with the tree the forum config is derived from
+    pub trees: Vec<ForumTree>,
+}
+
+/// Bond escrowed for a single entry, derived from the entry id
+#[account]
+pub struct EntryBond {
+    pub merkle_tree: Pubkey,
+    pub author: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub reclaimable_at: i64,
+}
+
+impl EntryBond {
+    pub const PREFIX: &'static str = "bond";
+    pub const SIZE: usize = 8 + 32 + 32 + 8 + 8 + 8;
+}
+
+/// Holds a forum's posting fees. Lamports are kept on this account and tokens in token
+/// accounts owned by it.
+#[account]
+pub struct ForumTreasury {
+    pub merkle_tree: Pubkey,
+}
+
+impl ForumTreasury {
+    pub const PREFIX: &'static str = "treasury";
+    pub const SIZE: usize = 8 + 32;
+}
+
+/// Marks the nonce of a `PostingPermit` signed by `signer` as used
+#[account]
+pub struct PermitNonce {
+    pub merkle_tree: Pubkey,
+    pub signer: Pubkey,
+    pub nonce: u64,
+}
+
+impl PermitNonce {
+    pub const PREFIX: &'static str = "permit";
+    pub const SIZE: usize = 8 + 32 + 32 + 8;
+}
+
+/// Tracks an author's recent entries in a forum for slow mode
+#[account]
+pub struct AuthorActivity {
+    pub merkle_tree: Pubkey,
+    pub author: Pubkey,
+    pub last_post_at: i64,
+    pub last_comment_at: i64,
+    /// Start of the current rolling window
+    pub window_start: i64,
+    /// Number of entries added since `window_start`
+    pub window_count: u32,
+}
+
+impl AuthorActivity {
+    pub const PREFIX: &'static str = "activity";
+    pub const SIZE: usize = 8 + 32 + 32 + 8 + 8 + 8 + 4;
+    pub const WINDOW: i64 = 24 * 60 * 60;
+
+    pub fn can_post(&self, slow_mode: &SlowMode, is_comment: bool, now: i64) -> bool {
+        let (last_entry_at, min_interval) = match is_comment {
+            true => (self.last_comment_at, slow_mode.min_comment_interval),
+            false => (self.last_post_at, slow_mode.min_post_interval),
+        };
+
+        if now.saturating_sub(last_entry_at) < i64::from(min_interval) {
+            return false;
+        }
+
+        slow_mode.max_entries_per_window == 0
+            || now.saturating_sub(self.window_start) >= Self::WINDOW
+            || self.window_count < slow_mode.max_entries_per_window
+    }
+
+    pub fn record_entry(&mut self, is_comment: bool, now: i64) {
+        if is_comment {
+            self.last_comment_at = now;
+        } else {
+            self.last_post_at = now;
+        }
+
+        if now.saturating_sub(self.window_start) >= Self::WINDOW {
+            self.window_start = now;
+            self.window_count = 0;
+        }
+
+        self.window_count = self.window_count.saturating_add(1);
+    }
+}
+
+#[event]
+pub struct SlowModeUpdated {
+    pub merkle_tree: Pubkey,
+    pub slow_mode: Option<SlowMode>,
+}
+
+#[event]
+pub struct ContentLimitsUpdated {
+    pub merkle_tree: Pubkey,
+    pub content_limits: ContentLimits,
+}
+
+#[event]
+pub struct StrictCommentsUpdated {
+    pub merkle_tree: Pubkey,
+    pub strict_comments: Option<StrictComments>,
+}
+
+#[event]
+pub struct UriRulesUpdated {
+    pub merkle_tree: Pubkey,
+    pub uri_rules: Vec<UriRule>,
+}
+
+#[event]
+pub struct PostingFeeUpdated {
+    pub merkle_tree: Pubkey,
+    pub posting_fee: Option<PostingFee>,
+}
+
+#[event]
+pub struct BondModeUpdated {
+    pub merkle_tree: Pubkey,
+    pub bond_mode: Option<BondMode>,
+}
+
+#[event]
+pub struct EntryBondClosed {
+    pub merkle_tree: Pubkey,
+    pub entry_id: Pubkey,
+    pub receiver: Pubkey,
+    /// Whether the bond was forfeited to the forum treasury
+    pub forfeited: bool,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub merkle_tree: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub receiver: Pubkey,
+}
+
+#[event]
+pub struct TreeAttached {
+    pub merkle_tree: Pubkey,
+    pub tree: Pubkey,
+    pub capacity: u64,
+}
+
+/// Identifies the tree an entry was appended to
+#[event]
+pub struct ShardAdded {
+    pub merkle_tree: Pubkey,
+    pub tree: Pubkey,
+    pub capacity: u64,
+}
+
+#[event]
+pub struct EntryAdded {
+    pub merkle_tree: Pubkey,
+    pub tree: Pubkey,
+    pub id: Pubkey,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct PollVoted {
+    pub merkle_tree: Pubkey,
+    pub poll: Pubkey,
+    pub voter: Pubkey,
+    pub option: u8,
+    /// Votes for `option` including this one
+    pub option_votes: u64,
+}
+
+#[event]
+pub struct EntryScoreChanged {
+    pub merkle_tree: Pubkey,
+    pub entry: Pubkey,
+    pub voter: Pubkey,
+    pub direction: VoteDirection,
+    /// Change to the entry's score, two when a vote is switched
+    pub delta: i64,
+}
+
+#[event]
+pub struct AuthorBanned {
+    pub merkle_tree: Pubkey,
+    pub author: Pubkey,
+    pub until: Option<i64>,
+    pub reason: u8,
+}
+
+#[event]
+pub struct AuthorUnbanned {
+    pub merkle_tree: Pubkey,
+    pub author: Pubkey,
+}
+
+#[event]
+pub struct ThreadLocked {
+    pub merkle_tree: Pubkey,
+    pub post: Pubkey,
+    pub until: Option<i64>,
+}
+
+#[event]
+pub struct ThreadUnlocked {
+    pub merkle_tree: Pubkey,
+    pub post: Pubkey,
+}
+
+/// Layout of `ForumConfig` before access policies were added, kept for migrating existing forums
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ForumConfigV0 {
+    pub total_capacity: u64,
+    pub post_count: u64,
+    pub admin: Pubkey,
+    pub flair: Vec<String>,
+    pub gate: Vec<GateV0>
 }
 
 impl ForumConfig {
-    pub fn get_size(flair: Vec<String>, gate: Option<Vec<Gate>>) -> usize {
+    /// When no policy is given the size of the policy built from `gate` is used
+    pub fn get_size(
+        flair: Vec<String>,
+        announcement_flair: &[String],
+        flair_policies: &[FlairPolicy],
+        gate: Option<Vec<Gate>>,
+        policy: Option<Vec<PolicyNode>>,
+        uri_rules: &[UriRule],
+        trees: usize,
+    ) -> usize {
+        let gate = gate.unwrap_or_default();
+        let policy = policy.unwrap_or_else(|| PolicyNode::from_gates(&gate));
         let base_size = BASE_FORUM_CONFIG_SIZE;
         let flair_size = 4 + flair.iter().fold(0, |acc, flair| acc + 4 + flair.len());
-        let gate_size = gate.unwrap_or(Vec::new()).iter().fold(0, |acc, gate| {
+        let announcement_flair_size = 4 + announcement_flair.iter().fold(0, |acc, flair| acc + 4 + flair.len());
+        let flair_policies_size = 4 + flair_policies.iter().fold(0, |acc, policy| acc + policy.get_size());
+        let gate_size = gate.iter().fold(0, |acc, gate| {
             acc + BASE_GATE_SIZE + gate.address.len() * 32
         });
+        let policy_size = 4 + policy.iter().fold(0, |acc, node| acc + node.get_size());
+        let uri_rules_size = 4 + uri_rules.iter().fold(0, |acc, rule| acc + rule.get_size());
+        let trees_size = 4 + trees * ForumTree::SIZE;
     
-        base_size + flair_size + gate_size
+        base_size
+            + flair_size
+            + announcement_flair_size
+            + flair_policies_size
+            + gate_size
+            + policy_size
+            + uri_rules_size
+            + trees_size
+    }
+
+    pub fn size(&self) -> usize {
+        Self::get_size(
+            self.flair.clone(),
+            &self.announcement_flair,
+            &self.flair_policies,
+            Some(self.gate.clone()),
+            Some(self.policy.clone()),
+            &self.uri_rules,
+            self.trees.len(),
+        )
+    }
+
+    pub fn from_v0(config: ForumConfigV0, merkle_tree: Pubkey) -> Self {
+        let gate = config.gate.into_iter().map(Gate::from_v0).collect::<Vec<Gate>>();
+        let policy = PolicyNode::from_gates(&gate);
+
+        Self {
+            total_capacity: config.total_capacity,
+            post_count: config.post_count,
+            admin: config.admin,
+            flair: config.flair,
+            announcement_flair: vec![],
+            flair_policies: vec![],
+            gate,
+            policy,
+            slow_mode: None,
+            posting_fee: None,
+            bond_mode: None,
+            content_limits: ContentLimits::default(),
+            strict_comments: None,
+            uri_rules: vec![],
+            trees: vec![ForumTree {
+                merkle_tree,
+                capacity: config.total_capacity,
+                shard: false,
+            }],
+        }
     }
 
     pub fn increment_post_count(&mut self) {
@@ -66,6 +723,173 @@ impl ForumConfig {
     pub fn set_admin(&mut self, admin: Pubkey) {
         self.admin = admin;
     }
+
+    /// Returns the first tree which still has capacity for new entries
+    pub fn get_active_tree(&self) -> Option<&ForumTree> {
+        let mut start = 0u64;
+
+        for tree in self.trees.iter().filter(|tree| !tree.shard) {
+            let end = start.saturating_add(tree.capacity);
+
+            if self.post_count < end {
+                return Some(tree);
+            }
+
+            start = end;
+        }
+
+        None
+    }
+
+    pub fn get_flair_policy(&self, flair: &str) -> Option<&FlairPolicy> {
+        self.flair_policies.iter().find(|policy| policy.flair == flair)
+    }
+
+    pub fn contains_tree(&self, merkle_tree: &Pubkey) -> bool {
+        self.trees.iter().any(|tree| tree.merkle_tree.eq(merkle_tree))
+    }
+
+    pub fn attach_tree(&mut self, merkle_tree: Pubkey, capacity: u64) {
+        self.trees.push(ForumTree { merkle_tree, capacity, shard: false });
+        self.total_capacity = self.total_capacity.saturating_add(capacity);
+    }
+
+    /// Shards are not counted towards `total_capacity`, which only tracks the trees filled in order
+    pub fn add_shard(&mut self, merkle_tree: Pubkey, capacity: u64) {
+        self.trees.push(ForumTree { merkle_tree, capacity, shard: true });
+    }
+}
+
+/// Whether a ban, lock or pin lasting until `until` still applies at `now`, `None` never expires
+pub fn is_active(until: Option<i64>, now: i64) -> bool {
+    match until {
+        Some(until) => now < until,
+        None => true,
+    }
+}
+
+/// Stops an author from posting to a forum, either permanently or until `until`
+#[account]
+pub struct Ban {
+    pub merkle_tree: Pubkey,
+    pub author: Pubkey,
+    pub until: Option<i64>,
+    pub reason: u8,
+}
+
+impl Ban {
+    pub const PREFIX: &'static str = "ban";
+    pub const SIZE: usize = 8 + 32 + 32 + 9 + 1;
+
+    pub fn is_active(&self, now: i64) -> bool {
+        is_active(self.until, now)
+    }
+}
+
+/// Stops new comments on a post, either permanently or until `until`
+#[account]
+pub struct ThreadLock {
+    pub merkle_tree: Pubkey,
+    pub post: Pubkey,
+    pub until: Option<i64>,
+}
+
+impl ThreadLock {
+    pub const PREFIX: &'static str = "lock";
+    pub const SIZE: usize = 8 + 32 + 32 + 9;
+
+    pub fn is_active(&self, now: i64) -> bool {
+        is_active(self.until, now)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Pin {
+    pub entry: Pubkey,
+    pub until: Option<i64>,
+}
+
+impl Pin {
+    pub const SIZE: usize = 32 + 9;
+
+    pub fn is_active(&self, now: i64) -> bool {
+        is_active(self.until, now)
+    }
+}
+
+/// Entries pinned to the top of a forum, in display order
+#[account]
+pub struct ForumPins {
+    pub merkle_tree: Pubkey,
+    pub pins: Vec<Pin>,
+}
+
+impl ForumPins {
+    pub const PREFIX: &'static str = "pins";
+    pub const MAX_PINS: usize = 5;
+    pub const SIZE: usize = 8 + 32 + 4 + Self::MAX_PINS * Pin::SIZE;
+
+    /// Pins `entry` at `position`, or last when `position` is out of range. An entry which is
+    /// already pinned is moved, and expired pins are dropped to make room.
+    pub fn pin(&mut self, entry: Pubkey, position: u8, until: Option<i64>, now: i64) -> Result<u8> {
+        self.pins.retain(|pin| pin.entry != entry && pin.is_active(now));
+        require_gt!(Self::MAX_PINS, self.pins.len(), OndaSocialError::TooManyPins);
+
+        let position = usize::from(position).min(self.pins.len());
+        self.pins.insert(position, Pin { entry, until });
+        Ok(position as u8)
+    }
+
+    pub fn unpin(&mut self, entry: &Pubkey) -> Result<()> {
+        let position = self.pins.iter().position(|pin| pin.entry == *entry).ok_or(OndaSocialError::PinNotFound)?;
+        self.pins.remove(position);
+        Ok(())
+    }
+}
+
+#[event]
+pub struct FlairAdded {
+    pub merkle_tree: Pubkey,
+    pub flair: String,
+}
+
+#[event]
+pub struct FlairRemoved {
+    pub merkle_tree: Pubkey,
+    pub flair: String,
+}
+
+#[event]
+pub struct AnnouncementFlairUpdated {
+    pub merkle_tree: Pubkey,
+    pub announcement_flair: Vec<String>,
+}
+
+#[event]
+pub struct FlairPoliciesUpdated {
+    pub merkle_tree: Pubkey,
+    pub flair_policies: Vec<FlairPolicy>,
+}
+
+#[event]
+pub struct EntryPinned {
+    pub merkle_tree: Pubkey,
+    pub entry: Pubkey,
+    pub position: u8,
+    pub until: Option<i64>,
+}
+
+#[event]
+pub struct EntryUnpinned {
+    pub merkle_tree: Pubkey,
+    pub entry: Pubkey,
+}
+
+#[event]
+pub struct GatesUpdated {
+    pub merkle_tree: Pubkey,
+    pub gate: Vec<Gate>,
+    pub policy: Vec<PolicyNode>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
@@ -75,6 +899,8 @@ pub enum OndaSocialEventType {
     Uninitialized,
     /// Leaf schema event.
     LeafSchemaEvent,
+    /// Inline body of an entry.
+    EntryBodyEvent,
 }
 
 
@@ -97,6 +923,26 @@ impl LeafSchemaEvent {
     }
 }
 
+/// Logged after the `LeafSchemaEvent` of entries carrying their body inline
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct EntryBodyEvent {
+    pub event_type: OndaSocialEventType,
+    pub version: Version,
+    pub id: Pubkey,
+    pub body: String,
+}
+
+impl EntryBodyEvent {
+    pub fn new(id: Pubkey, body: String) -> Self {
+        Self {
+            event_type: OndaSocialEventType::EntryBodyEvent,
+            version: Version::V1,
+            id,
+            body,
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Debug, Clone)]
 pub enum Version {
     V1,
@@ -125,6 +971,287 @@ pub enum DataV1 {
     Comment { post: Pubkey, parent: Option<Pubkey>, uri: String },
 }
 
+impl DataV1 {
+    /// Flag of the variant in `ContentLimits::entry_types`
+    pub fn entry_type(&self) -> u8 {
+        match self {
+            DataV1::TextPost { .. } => ContentLimits::TEXT_POST,
+            DataV1::ImagePost { .. } => ContentLimits::IMAGE_POST,
+            DataV1::LinkPost { .. } => ContentLimits::LINK_POST,
+            DataV1::VideoPost { .. } => ContentLimits::VIDEO_POST,
+            DataV1::Comment { .. } => ContentLimits::COMMENT,
+        }
+    }
+
+    pub fn flair(&self) -> Option<&String> {
+        match self {
+            DataV1::TextPost { flair, .. }
+            | DataV1::ImagePost { flair, .. }
+            | DataV1::LinkPost { flair, .. }
+            | DataV1::VideoPost { flair, .. } => flair.as_ref(),
+            DataV1::Comment { .. } => None,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub enum DigestAlgorithm {
+    Sha256,
+    Keccak256,
+}
+
+/// Off-chain entry body committed to by its digest, so that changes to the content
+/// behind `uri` can be detected
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ContentDigest {
+    pub uri: String,
+    pub mime_type: String,
+    pub algorithm: DigestAlgorithm,
+    pub digest: [u8; 32],
+}
+
+impl ContentDigest {
+    pub fn hash_content(algorithm: &DigestAlgorithm, content: &[u8]) -> [u8; 32] {
+        match algorithm {
+            DigestAlgorithm::Sha256 => hash::hashv(&[content]).to_bytes(),
+            DigestAlgorithm::Keccak256 => keccak::hashv(&[content]).to_bytes(),
+        }
+    }
+
+    pub fn matches(&self, content: &[u8]) -> bool {
+        Self::hash_content(&self.algorithm, content) == self.digest
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub enum DataV2 {
+    TextPost { title: String, content: ContentDigest, flair: Option<String>, nsfw: bool, spoiler: bool },
+    ImagePost { title: String, content: ContentDigest, flair: Option<String>, nsfw: bool, spoiler: bool },
+    LinkPost { title: String, content: ContentDigest, flair: Option<String>, nsfw: bool, spoiler: bool },
+    VideoPost { title: String, content: ContentDigest, flair: Option<String>, nsfw: bool, spoiler: bool },
+    /// `depth` is one for top level comments and is only verified in forums with strict comments
+    Comment { post: Pubkey, parent: Option<Pubkey>, depth: u8, content: ContentDigest },
+    /// Text post carried inline, up to `ContentLimits::max_body_len` bytes
+    ShortPost { title: String, body: String, flair: Option<String>, nsfw: bool, spoiler: bool },
+    /// Comment carried inline, up to `ContentLimits::max_body_len` bytes
+    ShortComment { post: Pubkey, parent: Option<Pubkey>, depth: u8, body: String },
+    /// Voted on with `vote_poll` until `closes_at`
+    PollPost {
+        title: String,
+        options: Vec<String>,
+        closes_at: Option<i64>,
+        flair: Option<String>,
+        nsfw: bool,
+        spoiler: bool,
+    },
+}
+
+impl DataV2 {
+    /// Flag of the variant in `ContentLimits::entry_types`, shared with the matching `DataV1` variant
+    pub fn entry_type(&self) -> u8 {
+        match self {
+            DataV2::TextPost { .. } => ContentLimits::TEXT_POST,
+            DataV2::ImagePost { .. } => ContentLimits::IMAGE_POST,
+            DataV2::LinkPost { .. } => ContentLimits::LINK_POST,
+            DataV2::VideoPost { .. } => ContentLimits::VIDEO_POST,
+            DataV2::Comment { .. } => ContentLimits::COMMENT,
+            DataV2::ShortPost { .. } => ContentLimits::TEXT_POST,
+            DataV2::ShortComment { .. } => ContentLimits::COMMENT,
+            DataV2::PollPost { .. } => ContentLimits::POLL_POST,
+        }
+    }
+
+    pub fn content(&self) -> Option<&ContentDigest> {
+        match self {
+            DataV2::TextPost { content, .. }
+            | DataV2::ImagePost { content, .. }
+            | DataV2::LinkPost { content, .. }
+            | DataV2::VideoPost { content, .. }
+            | DataV2::Comment { content, .. } => Some(content),
+            DataV2::ShortPost { .. } | DataV2::ShortComment { .. } | DataV2::PollPost { .. } => None,
+        }
+    }
+
+    pub fn flair(&self) -> Option<&String> {
+        match self {
+            DataV2::TextPost { flair, .. }
+            | DataV2::ImagePost { flair, .. }
+            | DataV2::LinkPost { flair, .. }
+            | DataV2::VideoPost { flair, .. }
+            | DataV2::ShortPost { flair, .. }
+            | DataV2::PollPost { flair, .. } => flair.as_ref(),
+            DataV2::Comment { .. } | DataV2::ShortComment { .. } => None,
+        }
+    }
+
+    /// Post, parent and depth of a comment
+    pub fn comment(&self) -> Option<(Pubkey, Option<Pubkey>, u8)> {
+        match self {
+            DataV2::Comment { post, parent, depth, .. }
+            | DataV2::ShortComment { post, parent, depth, .. } => Some((*post, *parent, *depth)),
+            _ => None,
+        }
+    }
+
+    pub fn body(&self) -> Option<&String> {
+        match self {
+            DataV2::ShortPost { body, .. } | DataV2::ShortComment { body, .. } => Some(body),
+            _ => None,
+        }
+    }
+}
+
+/// Data of an entry of any version
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub enum EntryData {
+    V1 { data: DataV1 },
+    V2 { data: DataV2 },
+}
+
+impl EntryData {
+    /// Hash of the data as stored in the entry's leaf
+    pub fn data_hash(&self) -> Result<[u8; 32]> {
+        let data = match self {
+            EntryData::V1 { data } => data.try_to_vec()?,
+            EntryData::V2 { data } => data.try_to_vec()?,
+        };
+        Ok(keccak::hashv(&[&data]).to_bytes())
+    }
+
+    pub fn entry_type(&self) -> u8 {
+        match self {
+            EntryData::V1 { data } => data.entry_type(),
+            EntryData::V2 { data } => data.entry_type(),
+        }
+    }
+
+    pub fn flair(&self) -> Option<&String> {
+        match self {
+            EntryData::V1 { data } => data.flair(),
+            EntryData::V2 { data } => data.flair(),
+        }
+    }
+
+    /// Body logged alongside the leaf, only `DataV2` entries carry one
+    pub fn body(&self) -> Option<&String> {
+        match self {
+            EntryData::V1 { .. } => None,
+            EntryData::V2 { data } => data.body(),
+        }
+    }
+
+    /// Post, parent and depth of a comment. The depth of `DataV1` comments is unknown.
+    pub fn comment(&self) -> Option<(Pubkey, Option<Pubkey>, Option<u8>)> {
+        match self {
+            EntryData::V1 { data: DataV1::Comment { post, parent, .. } } => Some((*post, *parent, None)),
+            EntryData::V1 { .. } => None,
+            EntryData::V2 { data } => data.comment().map(|(post, parent, depth)| (post, parent, Some(depth))),
+        }
+    }
+}
+
+/// Parent of a new comment, verified against the last `leaf.proof_len` remaining accounts
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ParentEntry {
+    pub leaf: EntryLeafArgs,
+    pub data: EntryData,
+}
+
+/// Identifies an existing entry leaf, verified against the last `proof_len` remaining accounts
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct EntryLeafArgs {
+    pub root: [u8; 32],
+    pub author: Pubkey,
+    pub created_at: i64,
+    pub edited_at: Option<i64>,
+    pub nonce: u64,
+    pub index: u32,
+    pub proof_len: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum VoteDirection {
+    Up,
+    Down,
+}
+
+impl VoteDirection {
+    pub fn score(&self) -> i64 {
+        match self {
+            VoteDirection::Up => 1,
+            VoteDirection::Down => -1,
+        }
+    }
+}
+
+/// Current vote of `voter` on an entry, derived from the entry id
+#[account]
+pub struct EntryVote {
+    pub merkle_tree: Pubkey,
+    pub entry: Pubkey,
+    pub voter: Pubkey,
+    pub direction: VoteDirection,
+    pub voted_at: i64,
+}
+
+impl EntryVote {
+    pub const PREFIX: &'static str = "entry_vote";
+    pub const SIZE: usize = 8 + 32 + 32 + 32 + 1 + 8;
+
+    /// Records a vote and returns the resulting change to the entry's score. Votes can be
+    /// switched, but the same vote cannot be cast twice.
+    pub fn record_vote(&mut self, direction: VoteDirection, now: i64) -> Option<i64> {
+        let is_new = self.voter == Pubkey::default();
+        let delta = match is_new {
+            true => direction.score(),
+            false if self.direction == direction => return None,
+            false => direction.score() * 2,
+        };
+
+        self.direction = direction;
+        self.voted_at = now;
+        Some(delta)
+    }
+}
+
+/// Marks that `voter` has voted on a poll, derived from the poll's entry id
+#[account]
+pub struct PollVote {
+    pub merkle_tree: Pubkey,
+    pub poll: Pubkey,
+    pub voter: Pubkey,
+    pub option: u8,
+    pub voted_at: i64,
+}
+
+impl PollVote {
+    pub const PREFIX: &'static str = "poll_vote";
+    pub const SIZE: usize = 8 + 32 + 32 + 32 + 1 + 8;
+}
+
+/// Running vote count of each poll option, derived from the poll's entry id
+#[account]
+pub struct PollTally {
+    pub merkle_tree: Pubkey,
+    pub poll: Pubkey,
+    pub votes: Vec<u64>,
+}
+
+impl PollTally {
+    pub const PREFIX: &'static str = "poll_tally";
+    pub const SIZE: usize = 8 + 32 + 32 + 4 + 8 * crate::MAX_POLL_OPTIONS;
+
+    pub fn record_vote(&mut self, option_count: usize, option: u8) -> u64 {
+        if self.votes.len() < option_count {
+            self.votes.resize(option_count, 0);
+        }
+
+        let votes = &mut self.votes[usize::from(option)];
+        *votes = votes.saturating_add(1);
+        *votes
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Debug, Clone)]
 pub enum LeafSchema {
     V1 {
@@ -1,5 +1,9 @@
 use anchor_lang::prelude::*;
-use onda_compression::{self, program::OndaCompression};
+use onda_compression::{
+    self,
+    program::OndaCompression,
+    state::{Gate, PolicyNode, SlowMode, PostingFee, BondMode, ContentLimits, UriRule, StrictComments, DataV2, EntryData, Credential, CompressedNftArgs, AllowlistProof, FlairPolicy},
+};
 
 use crate::{state::*, error::*};
 pub mod state;
@@ -78,6 +82,7 @@ pub struct DeleteEntry<'info> {
         bump,
     )]
     pub team: Account<'info, Team>,
+    #[account(mut)]
     /// CHECK: checked in cpi
     pub author: UncheckedAccount<'info>,
     /// CHECK: checked in cpi
@@ -85,14 +90,292 @@ pub struct DeleteEntry<'info> {
     #[account(mut)]
     /// CHECK: constrained by seeds
     pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub entry_tree: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub entry_bond: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub treasury: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub log_wrapper: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    /// CHECK: checked in cpi
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateForumConfig<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTreasuryConfig<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub treasury: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BanAuthor<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    /// CHECK: checked in cpi
+    pub author: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub ban: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LockThread<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub thread_lock: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PinEntry<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub forum_pins: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddAnnouncement<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    /// CHECK: checked in cpi
+    pub ban: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub author_activity: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub treasury: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub fee_mint: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub fee_token_account: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub active_tree: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub entry_bond: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub log_wrapper: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    /// CHECK: checked in cpi
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EditAnnouncement<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    /// CHECK: checked in cpi
+    pub ban: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub entry_tree: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub permit_nonce: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub log_wrapper: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
+    /// CHECK: checked in cpi
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AttachTree<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub new_tree: UncheckedAccount<'info>,
     /// CHECK: checked in cpi
     pub log_wrapper: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub compression_program: UncheckedAccount<'info>,
     pub onda_compression: Program<'info, OndaCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddShard<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub new_tree: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub shard: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub log_wrapper: UncheckedAccount<'info>,
     /// CHECK: checked in cpi
     pub compression_program: UncheckedAccount<'info>,
+    pub onda_compression: Program<'info, OndaCompression>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    pub member: Signer<'info>,
+    #[account(
+        seeds = [Team::PREFIX.as_bytes(), merkle_tree.key().as_ref()],
+        bump,
+    )]
+    pub team: Account<'info, Team>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub receiver: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub forum_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub treasury: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: checked in cpi
+    pub mint: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub treasury_token_account: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    /// CHECK: checked in cpi
+    pub receiver_token_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in cpi
+    pub token_program: Option<UncheckedAccount<'info>>,
+    pub onda_compression: Program<'info, OndaCompression>,
+}
+
 #[program]
 pub mod onda_moderation {
     use super::*;
@@ -179,6 +462,480 @@ pub mod onda_moderation {
         Ok(())
     }
 
+    pub fn add_flair(ctx: Context<UpdateForumConfig>, flair: String) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::add_flair(cpi_ctx, flair)
+    }
+
+    pub fn remove_flair(ctx: Context<UpdateForumConfig>, flair: String) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::remove_flair(cpi_ctx, flair)
+    }
+
+    pub fn set_announcement_flair(ctx: Context<UpdateForumConfig>, announcement_flair: Vec<String>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_announcement_flair(cpi_ctx, announcement_flair)
+    }
+
+    pub fn set_flair_policies(ctx: Context<UpdateForumConfig>, flair_policies: Vec<FlairPolicy>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_flair_policies(cpi_ctx, flair_policies)
+    }
+
+    pub fn set_gates(ctx: Context<UpdateForumConfig>, gate: Vec<Gate>, policy: Option<Vec<PolicyNode>>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_gates(cpi_ctx, gate, policy)
+    }
+
+    pub fn set_slow_mode(ctx: Context<UpdateForumConfig>, slow_mode: Option<SlowMode>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_slow_mode(cpi_ctx, slow_mode)
+    }
+
+    pub fn set_posting_fee(ctx: Context<UpdateTreasuryConfig>, posting_fee: Option<PostingFee>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_treasury_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_posting_fee(cpi_ctx, posting_fee)
+    }
+
+    pub fn set_bond_mode(ctx: Context<UpdateTreasuryConfig>, bond_mode: Option<BondMode>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_treasury_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_bond_mode(cpi_ctx, bond_mode)
+    }
+
+    pub fn set_content_limits(ctx: Context<UpdateForumConfig>, content_limits: ContentLimits) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_content_limits(cpi_ctx, content_limits)
+    }
+
+    pub fn set_uri_rules(ctx: Context<UpdateForumConfig>, uri_rules: Vec<UriRule>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_uri_rules(cpi_ctx, uri_rules)
+    }
+
+    pub fn set_strict_comments(ctx: Context<UpdateForumConfig>, strict_comments: Option<StrictComments>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            update_forum_config_accounts(ctx.accounts),
+            signer_seeds
+        );
+
+        onda_compression::cpi::set_strict_comments(cpi_ctx, strict_comments)
+    }
+
+    pub fn ban_author(ctx: Context<BanAuthor>, until: Option<i64>, reason: u8) -> Result<()> {
+        assert_member(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::BanAuthor {
+            admin: ctx.accounts.team.to_account_info(),
+            payer: ctx.accounts.member.to_account_info(),
+            author: ctx.accounts.author.to_account_info(),
+            ban: ctx.accounts.ban.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::ban_author(cpi_ctx, until, reason)
+    }
+
+    pub fn unban_author(ctx: Context<BanAuthor>) -> Result<()> {
+        assert_member(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::UnbanAuthor {
+            admin: ctx.accounts.team.to_account_info(),
+            receiver: ctx.accounts.member.to_account_info(),
+            author: ctx.accounts.author.to_account_info(),
+            ban: ctx.accounts.ban.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::unban_author(cpi_ctx)
+    }
+
+    pub fn lock_thread(ctx: Context<LockThread>, post: Pubkey, until: Option<i64>) -> Result<()> {
+        assert_member(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::LockThread {
+            admin: ctx.accounts.team.to_account_info(),
+            payer: ctx.accounts.member.to_account_info(),
+            thread_lock: ctx.accounts.thread_lock.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::lock_thread(cpi_ctx, post, until)
+    }
+
+    pub fn unlock_thread(ctx: Context<LockThread>, post: Pubkey) -> Result<()> {
+        assert_member(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::UnlockThread {
+            admin: ctx.accounts.team.to_account_info(),
+            receiver: ctx.accounts.member.to_account_info(),
+            thread_lock: ctx.accounts.thread_lock.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::unlock_thread(cpi_ctx, post)
+    }
+
+    pub fn pin_entry(ctx: Context<PinEntry>, entry: Pubkey, position: u8, until: Option<i64>) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::PinEntry {
+            admin: ctx.accounts.team.to_account_info(),
+            payer: ctx.accounts.member.to_account_info(),
+            forum_pins: ctx.accounts.forum_pins.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::pin_entry(cpi_ctx, entry, position, until)
+    }
+
+    pub fn unpin_entry(ctx: Context<PinEntry>, entry: Pubkey) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::UnpinEntry {
+            admin: ctx.accounts.team.to_account_info(),
+            forum_pins: ctx.accounts.forum_pins.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::unpin_entry(cpi_ctx, entry)
+    }
+
+    /// Adds an entry authored by a member and co-signed by the team, so that it may use the
+    /// forum's announcement flair
+    pub fn add_announcement<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddAnnouncement<'info>>,
+        data: DataV2,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        assert_member(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::AddEntry {
+            author: ctx.accounts.member.to_account_info(),
+            session_token: None,
+            signer: ctx.accounts.member.to_account_info(),
+            additional_signer: None,
+            forum_admin: Some(ctx.accounts.team.to_account_info()),
+            ban: ctx.accounts.ban.to_account_info(),
+            author_activity: ctx.accounts.author_activity.as_ref().map(|account| account.to_account_info()),
+            thread_lock: None,
+            treasury: ctx.accounts.treasury.as_ref().map(|account| account.to_account_info()),
+            fee_mint: ctx.accounts.fee_mint.as_ref().map(|account| account.to_account_info()),
+            fee_token_account: ctx.accounts.fee_token_account.as_ref().map(|account| account.to_account_info()),
+            treasury_token_account: ctx.accounts.treasury_token_account.as_ref().map(|account| account.to_account_info()),
+            token_program: ctx.accounts.token_program.as_ref().map(|account| account.to_account_info()),
+            instructions: ctx.accounts.instructions.as_ref().map(|account| account.to_account_info()),
+            permit_nonce: ctx.accounts.permit_nonce.as_ref().map(|account| account.to_account_info()),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            active_tree: ctx.accounts.active_tree.as_ref().map(|account| account.to_account_info()),
+            parent_tree: None,
+            entry_bond: ctx.accounts.entry_bond.as_ref().map(|account| account.to_account_info()),
+            log_wrapper: ctx.accounts.log_wrapper.to_account_info(),
+            compression_program: ctx.accounts.compression_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+
+        onda_compression::cpi::add_entry_v2(cpi_ctx, data, credentials, compressed_nfts, allowlist_proofs, None)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn edit_announcement<'info>(
+        ctx: Context<'_, '_, '_, 'info, EditAnnouncement<'info>>,
+        root: [u8; 32],
+        created_at: i64,
+        edited_at: Option<i64>,
+        previous_data: EntryData,
+        nonce: u64,
+        index: u32,
+        data: DataV2,
+        credentials: Vec<Credential>,
+        compressed_nfts: Vec<CompressedNftArgs>,
+        allowlist_proofs: Vec<AllowlistProof>,
+    ) -> Result<()> {
+        assert_member(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::EditEntry {
+            author: ctx.accounts.member.to_account_info(),
+            session_token: None,
+            signer: ctx.accounts.member.to_account_info(),
+            additional_signer: None,
+            forum_admin: Some(ctx.accounts.team.to_account_info()),
+            ban: ctx.accounts.ban.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            entry_tree: ctx.accounts.entry_tree.as_ref().map(|account| account.to_account_info()),
+            thread_lock: None,
+            instructions: ctx.accounts.instructions.as_ref().map(|account| account.to_account_info()),
+            permit_nonce: ctx.accounts.permit_nonce.as_ref().map(|account| account.to_account_info()),
+            log_wrapper: ctx.accounts.log_wrapper.to_account_info(),
+            compression_program: ctx.accounts.compression_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+
+        onda_compression::cpi::edit_entry_v2(
+            cpi_ctx,
+            root,
+            created_at,
+            edited_at,
+            previous_data,
+            nonce,
+            index,
+            data,
+            credentials,
+            compressed_nfts,
+            allowlist_proofs,
+        )
+    }
+
+    pub fn attach_tree(ctx: Context<AttachTree>, max_depth: u32, max_buffer_size: u32) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::AttachTree {
+            admin: ctx.accounts.team.to_account_info(),
+            payer: ctx.accounts.member.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            new_tree: ctx.accounts.new_tree.to_account_info(),
+            log_wrapper: ctx.accounts.log_wrapper.to_account_info(),
+            compression_program: ctx.accounts.compression_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::attach_tree(cpi_ctx, max_depth, max_buffer_size)
+    }
+
+    pub fn add_shard(ctx: Context<AddShard>, max_depth: u32, max_buffer_size: u32) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::AddShard {
+            admin: ctx.accounts.team.to_account_info(),
+            payer: ctx.accounts.member.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            new_tree: ctx.accounts.new_tree.to_account_info(),
+            shard: ctx.accounts.shard.to_account_info(),
+            log_wrapper: ctx.accounts.log_wrapper.to_account_info(),
+            compression_program: ctx.accounts.compression_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::add_shard(cpi_ctx, max_depth, max_buffer_size)
+    }
+
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        assert_admin(&ctx.accounts.team, &ctx.accounts.member)?;
+
+        let cpi_accounts = onda_compression::cpi::accounts::WithdrawTreasury {
+            admin: ctx.accounts.team.to_account_info(),
+            receiver: ctx.accounts.receiver.to_account_info(),
+            forum_config: ctx.accounts.forum_config.to_account_info(),
+            treasury: ctx.accounts.treasury.to_account_info(),
+            merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+            mint: ctx.accounts.mint.as_ref().map(|account| account.to_account_info()),
+            treasury_token_account: ctx.accounts.treasury_token_account.as_ref().map(|account| account.to_account_info()),
+            receiver_token_account: ctx.accounts.receiver_token_account.as_ref().map(|account| account.to_account_info()),
+            token_program: ctx.accounts.token_program.as_ref().map(|account| account.to_account_info()),
+        };
+
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
+        let signer_seeds = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.onda_compression.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        onda_compression::cpi::withdraw_treasury(cpi_ctx, amount)
+    }
+
     pub fn delete_entry<'info>(
         ctx: Context<'_, '_, '_, 'info, DeleteEntry<'info>>,
         root: [u8; 32],
@@ -193,24 +950,21 @@ pub mod onda_moderation {
 
         assert_member(team, member)?;
 
-        let cpi_program = ctx.accounts.compression_program.to_account_info();
+        let cpi_program = ctx.accounts.onda_compression.to_account_info();
         let cpi_accounts = onda_compression::cpi::accounts::DeleteEntry {
                 signer: team.to_account_info(),
                 author: ctx.accounts.author.to_account_info(),
                 forum_config: ctx.accounts.forum_config.to_account_info(),
                 merkle_tree: ctx.accounts.merkle_tree.to_account_info(),
+                entry_tree: ctx.accounts.entry_tree.as_ref().map(|account| account.to_account_info()),
+                entry_bond: ctx.accounts.entry_bond.to_account_info(),
+                treasury: ctx.accounts.treasury.as_ref().map(|account| account.to_account_info()),
                 log_wrapper: ctx.accounts.log_wrapper.to_account_info(),
                 compression_program: ctx.accounts.compression_program.to_account_info(),
                 system_program: ctx.accounts.system_program.to_account_info(),  
         };
 
-        let bump = *ctx.bumps.get("team").unwrap();
-        let merkle_tree_key = ctx.accounts.merkle_tree.key();
-        let seeds = &[
-            Team::PREFIX.as_bytes(),
-            merkle_tree_key.as_ref(),
-            &[bump]
-        ];
+        let seeds = team_seeds(&ctx.accounts.merkle_tree, &ctx.bumps["team"]);
         let signer_seeds = &[&seeds[..]];
         let mut cpi_ctx = CpiContext::new_with_signer(
             cpi_program,
@@ -236,4 +990,49 @@ pub mod onda_moderation {
 pub fn assert_member(team: &Team, member: &AccountInfo) -> Result<()> {
     team.members.iter().find(|m| m.address.eq(&member.key())).ok_or(ErrorCodes::MemberNotFound)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+// Only admins or owners can change the forum config
+pub fn assert_admin(team: &Team, member: &AccountInfo) -> Result<()> {
+    let member_role = team.members.iter().find(|m| m.address.eq(&member.key())).ok_or(ErrorCodes::Unauthorized)?;
+
+    match member_role.role {
+        Role::Owner => Ok(()),
+        Role::Admin => Ok(()),
+        _ => err!(ErrorCodes::Unauthorized),
+    }
+}
+
+/// Signer seeds of the team, which administers the forum of `merkle_tree`
+fn team_seeds<'a>(merkle_tree: &'a AccountInfo, bump: &'a u8) -> [&'a [u8]; 3] {
+    [
+        Team::PREFIX.as_bytes(),
+        merkle_tree.key.as_ref(),
+        std::slice::from_ref(bump),
+    ]
+}
+
+fn update_forum_config_accounts<'info>(
+    accounts: &UpdateForumConfig<'info>,
+) -> onda_compression::cpi::accounts::UpdateForumConfig<'info> {
+    onda_compression::cpi::accounts::UpdateForumConfig {
+        admin: accounts.team.to_account_info(),
+        payer: accounts.member.to_account_info(),
+        forum_config: accounts.forum_config.to_account_info(),
+        merkle_tree: accounts.merkle_tree.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+    }
+}
+
+fn update_treasury_config_accounts<'info>(
+    accounts: &UpdateTreasuryConfig<'info>,
+) -> onda_compression::cpi::accounts::UpdateTreasuryConfig<'info> {
+    onda_compression::cpi::accounts::UpdateTreasuryConfig {
+        admin: accounts.team.to_account_info(),
+        payer: accounts.member.to_account_info(),
+        forum_config: accounts.forum_config.to_account_info(),
+        treasury: accounts.treasury.to_account_info(),
+        merkle_tree: accounts.merkle_tree.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+    }
+}
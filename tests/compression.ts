@@ -1,4 +1,6 @@
 import assert from "assert";
+import { createHash } from "crypto";
+import { keccak_256 } from "js-sha3";
 import {
   createVerifyLeafIx,
   ConcurrentMerkleTreeAccount,
@@ -27,6 +29,1022 @@ describe("Compression", () => {
     assert.equal(forumConfigAccount.gate.length, 0, "forum.gate");
   });
 
+  it("Adds and removes flair", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      payer: admin.publicKey,
+      forumConfig: forumConfigPda,
+      merkleTree: merkleTree.publicKey,
+    };
+    await program.methods
+      .addFlair("seasonal")
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+
+    let forumConfigAccount = await program.account.forumConfig.fetch(
+      forumConfigPda
+    );
+    assert.deepEqual(forumConfigAccount.flair, ["test", "test2", "seasonal"]);
+
+    await program.methods
+      .removeFlair("test")
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+
+    forumConfigAccount = await program.account.forumConfig.fetch(
+      forumConfigPda
+    );
+    assert.deepEqual(forumConfigAccount.flair, ["test2", "seasonal"]);
+  });
+
+  it("Restricts announcement flair to the admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const data: helpers.DataV1 = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: "test",
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    await program.methods
+      .setAnnouncementFlair(["test"])
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, data);
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(
+        err.error.errorMessage,
+        "Announcement flair requires the forum admin"
+      );
+    }
+
+    // Co-signing as an additional signer is reserved for gates
+    try {
+      await helpers.addEntry(
+        merkleTree.publicKey,
+        data,
+        undefined,
+        [],
+        null,
+        admin
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(
+        err.error.errorMessage,
+        "Announcement flair requires the forum admin"
+      );
+    }
+
+    await helpers.addEntry(
+      merkleTree.publicKey,
+      data,
+      undefined,
+      [],
+      null,
+      null,
+      admin
+    );
+  });
+
+  it("Restricts flair to its policy", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const post = (flair: string): helpers.DataV1 => ({
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      payer: admin.publicKey,
+      forumConfig: forumConfigPda,
+      merkleTree: merkleTree.publicKey,
+    };
+    // The gate is only referenced by the flair policy, not the forum's policy
+    await program.methods
+      .setGates(
+        [
+          {
+            minAmount: new anchor.BN(1),
+            maxAmount: null,
+            decimals: null,
+            address: [admin.publicKey],
+            ruleType: { additionalSigner: {} },
+            // @ts-ignore
+            operator: { and: {} },
+          },
+        ],
+        []
+      )
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .setFlairPolicies([{ flair: "test2", policy: [{ gate: { index: 0 } }] }])
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+
+    await helpers.addEntry(merkleTree.publicKey, post("test"));
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, post("test2"));
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Not allowed to use this flair");
+    }
+
+    await helpers.addEntry(
+      merkleTree.publicKey,
+      post("test2"),
+      undefined,
+      [],
+      null,
+      admin
+    );
+  });
+
+  it("Edits entries with token gated flair", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const post = (title: string, flair: string): helpers.DataV1 => ({
+      textPost: {
+        title,
+        uri: "https://example.com",
+        flair,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const mintAddress = await splToken.createMint(
+      helpers.connection,
+      admin,
+      admin.publicKey,
+      admin.publicKey,
+      0
+    );
+    const tokenAccount = await splToken.getOrCreateAssociatedTokenAccount(
+      helpers.connection,
+      admin,
+      mintAddress,
+      author.publicKey
+    );
+    await splToken.mintTo(
+      helpers.connection,
+      admin,
+      mintAddress,
+      tokenAccount.address,
+      admin.publicKey,
+      1
+    );
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      payer: admin.publicKey,
+      forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+      merkleTree: merkleTree.publicKey,
+    };
+    // The gate is only referenced by the flair policy, not the forum's policy
+    await program.methods
+      .setGates(
+        [
+          {
+            minAmount: new anchor.BN(1),
+            maxAmount: null,
+            decimals: null,
+            address: [mintAddress],
+            ruleType: { token: {} },
+            // @ts-ignore
+            operator: { and: {} },
+          },
+        ],
+        []
+      )
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .setFlairPolicies([{ flair: "test2", policy: [{ gate: { index: 0 } }] }])
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+
+    const credentials = [
+      { gate: 0, accounts: [mintAddress, tokenAccount.address] },
+    ];
+    const leafEvent = await helpers.addEntry(
+      merkleTree.publicKey,
+      post("test", "test2"),
+      author,
+      credentials
+    );
+
+    // Keeping the flair needs no credentials
+    const keptFlair = await helpers.editEntry(
+      merkleTree.publicKey,
+      author,
+      [leafEvent],
+      0,
+      { v1: { data: post("test", "test2") } },
+      post("kept flair", "test2")
+    );
+    const removedFlair = await helpers.editEntry(
+      merkleTree.publicKey,
+      author,
+      [keptFlair],
+      0,
+      { v1: { data: post("kept flair", "test2") } },
+      post("removed flair", "test")
+    );
+
+    try {
+      await helpers.editEntry(
+        merkleTree.publicKey,
+        author,
+        [removedFlair],
+        0,
+        { v1: { data: post("removed flair", "test") } },
+        post("restored flair", "test2")
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Not allowed to use this flair");
+    }
+
+    await helpers.editEntry(
+      merkleTree.publicKey,
+      author,
+      [removedFlair],
+      0,
+      { v1: { data: post("removed flair", "test") } },
+      post("restored flair", "test2"),
+      credentials
+    );
+  });
+
+  it("Pins and unpins entries", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumPinsPda = helpers.findForumPinsPda(merkleTree.publicKey);
+    const entries = [
+      anchor.web3.Keypair.generate().publicKey,
+      anchor.web3.Keypair.generate().publicKey,
+    ];
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      forumPins: forumPinsPda,
+      forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+      merkleTree: merkleTree.publicKey,
+    };
+    await program.methods
+      .pinEntry(entries[0], 0, null)
+      .accounts({ ...accounts, payer: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .pinEntry(entries[1], 0, new anchor.BN(2_000_000_000))
+      .accounts({ ...accounts, payer: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    let forumPinsAccount = await program.account.forumPins.fetch(forumPinsPda);
+    assert.deepEqual(
+      forumPinsAccount.pins.map((pin) => pin.entry.toBase58()),
+      [entries[1].toBase58(), entries[0].toBase58()]
+    );
+
+    await program.methods
+      .unpinEntry(entries[1])
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+
+    forumPinsAccount = await program.account.forumPins.fetch(forumPinsPda);
+    assert.equal(forumPinsAccount.pins.length, 1);
+    assert.ok(forumPinsAccount.pins[0].entry.equals(entries[0]));
+  });
+
+  it("Bans and unbans an author", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const data: helpers.DataV1 = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, data, author);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      author: author.publicKey,
+      ban: helpers.findBanPda(merkleTree.publicKey, author.publicKey),
+      forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+      merkleTree: merkleTree.publicKey,
+    };
+    await program.methods
+      .banAuthor(null, 1)
+      .accounts({ ...accounts, payer: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, data, author);
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Author is banned");
+    }
+
+    // Banned authors cannot edit their existing entries either
+    try {
+      await helpers.editEntry(
+        merkleTree.publicKey,
+        author,
+        [leafEvent],
+        0,
+        { v1: { data } },
+        { textPost: { ...data.textPost, title: "edited" } }
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Author is banned");
+    }
+
+    await program.methods
+      .unbanAuthor()
+      .accounts({ ...accounts, receiver: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await helpers.addEntry(merkleTree.publicKey, data, author);
+  });
+
+  it("Locks and unlocks a thread", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const commenter = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+    const comment: helpers.DataV1 = {
+      comment: {
+        post: leafEvent.id,
+        parent: null,
+        uri: "https://example.com",
+      },
+    };
+    const commentEvent = await helpers.addEntry(
+      merkleTree.publicKey,
+      comment,
+      commenter
+    );
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      threadLock: helpers.findThreadLockPda(
+        merkleTree.publicKey,
+        leafEvent.id
+      ),
+      forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+      merkleTree: merkleTree.publicKey,
+    };
+    await program.methods
+      .lockThread(leafEvent.id, null)
+      .accounts({ ...accounts, payer: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, comment);
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Thread is locked");
+    }
+
+    // Existing comments cannot be edited either
+    try {
+      await helpers.editEntry(
+        merkleTree.publicKey,
+        commenter,
+        [leafEvent, commentEvent],
+        1,
+        { v1: { data: comment } },
+        { comment: { ...comment.comment, uri: "https://example.com/edited" } }
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Thread is locked");
+    }
+
+    await program.methods
+      .unlockThread(leafEvent.id)
+      .accounts({ ...accounts, receiver: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await helpers.addEntry(merkleTree.publicKey, comment);
+  });
+
+  it("Enforces slow mode", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const data: helpers.DataV1 = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    await program.methods
+      .setSlowMode({
+        minPostInterval: 3600,
+        minCommentInterval: 0,
+        maxEntriesPerWindow: 0,
+      })
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, data, author);
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, data, author);
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Posting too frequently");
+    }
+
+    await helpers.addEntry(
+      merkleTree.publicKey,
+      {
+        comment: {
+          post: leafEvent.id,
+          parent: null,
+          uri: "https://example.com",
+        },
+      },
+      author
+    );
+  });
+
+  it("Enforces forum content limits", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    await program.methods
+      .setContentLimits({
+        maxTitleLen: 100,
+        maxUriLen: 128,
+        maxFlairLen: 42,
+        maxBodyLen: 280,
+        // Text posts and comments only
+        entryTypes: (1 << 0) | (1 << 4),
+        allowNsfw: false,
+      })
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const post = {
+      title: "test",
+      uri: "https://example.com",
+      flair: null,
+      nsfw: false,
+      spoiler: false,
+    };
+
+    await helpers.addEntry(merkleTree.publicKey, { textPost: post }, author);
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, { videoPost: post }, author);
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Entry type not enabled in this forum");
+    }
+
+    try {
+      await helpers.addEntry(
+        merkleTree.publicKey,
+        { textPost: { ...post, nsfw: true } },
+        author
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Nsfw entries not allowed in this forum");
+    }
+  });
+
+  it("Enforces forum uri rules", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    await program.methods
+      .setUriRules([
+        {
+          // Link posts only
+          entryTypes: 1 << 2,
+          schemes: ["https"],
+          hosts: ["example.com"],
+        },
+      ])
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const post = {
+      title: "test",
+      uri: "https://example.com",
+      flair: null,
+      nsfw: false,
+      spoiler: false,
+    };
+
+    await helpers.addEntry(merkleTree.publicKey, { linkPost: post }, author);
+
+    try {
+      await helpers.addEntry(
+        merkleTree.publicKey,
+        { linkPost: { ...post, uri: "javascript:alert(1)" } },
+        author
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Uri scheme not allowed in this forum");
+    }
+
+    try {
+      await helpers.addEntry(
+        merkleTree.publicKey,
+        { linkPost: { ...post, uri: "https://example.org" } },
+        author
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Uri host not allowed in this forum");
+    }
+  });
+
+  it("Collects posting fees into the treasury", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const treasury = helpers.findTreasuryPda(merkleTree.publicKey);
+    const postAmount = anchor.web3.LAMPORTS_PER_SOL / 100;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const accounts = {
+      admin: admin.publicKey,
+      forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+      treasury,
+      merkleTree: merkleTree.publicKey,
+    };
+    await program.methods
+      .setPostingFee({
+        mint: null,
+        postAmount: new anchor.BN(postAmount),
+        commentAmount: new anchor.BN(0),
+      })
+      .accounts({ ...accounts, payer: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const balanceBefore = await helpers.connection.getBalance(treasury);
+    await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+    const balanceAfter = await helpers.connection.getBalance(treasury);
+    assert.equal(balanceAfter - balanceBefore, postAmount);
+
+    await program.methods
+      .withdrawTreasury(new anchor.BN(postAmount))
+      .accounts({
+        ...accounts,
+        receiver: admin.publicKey,
+        mint: null,
+        treasuryTokenAccount: null,
+        receiverTokenAccount: null,
+        tokenProgram: null,
+      })
+      .rpc({ commitment: "confirmed" });
+    assert.equal(await helpers.connection.getBalance(treasury), balanceBefore);
+  });
+
+  it("Reclaims an entry bond", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const bondAmount = anchor.web3.LAMPORTS_PER_SOL / 10;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const adminProgram = await helpers.getCompressionProgram(admin);
+    await adminProgram.methods
+      .setBondMode({
+        amount: new anchor.BN(bondAmount),
+        reclaimWindow: new anchor.BN(0),
+      })
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        treasury: helpers.findTreasuryPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await helpers.addEntry(
+      merkleTree.publicKey,
+      {
+        textPost: {
+          title: "test",
+          uri: "https://example.com",
+          flair: null,
+          nsfw: false,
+          spoiler: false,
+        },
+      },
+      author
+    );
+    const entryBond = helpers.findEntryBondPda(leafEvent.id);
+    const entryBondAccount =
+      await adminProgram.account.entryBond.fetch(entryBond);
+    assert.equal(entryBondAccount.amount.toNumber(), bondAmount);
+
+    const authorProgram = await helpers.getCompressionProgram(author);
+    await authorProgram.methods
+      .reclaimBond(leafEvent.id)
+      .accounts({
+        author: author.publicKey,
+        entryBond,
+      })
+      .rpc({ commitment: "confirmed" });
+    assert.equal(await helpers.connection.getAccountInfo(entryBond), null);
+  });
+
+  it("Forfeits the bond of an entry its author deletes early", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const treasury = helpers.findTreasuryPda(merkleTree.publicKey);
+    const bondAmount = anchor.web3.LAMPORTS_PER_SOL / 10;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const adminProgram = await helpers.getCompressionProgram(admin);
+    await adminProgram.methods
+      .setBondMode({
+        amount: new anchor.BN(bondAmount),
+        reclaimWindow: new anchor.BN(3600),
+      })
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: forumConfigPda,
+        treasury,
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await helpers.addEntry(
+      merkleTree.publicKey,
+      {
+        textPost: {
+          title: "test",
+          uri: "https://example.com",
+          flair: null,
+          nsfw: false,
+          spoiler: false,
+        },
+      },
+      author
+    );
+    const entryBond = helpers.findEntryBondPda(leafEvent.id);
+    const leafHash = helpers.computeCompressedEntryHash(
+      leafEvent.id,
+      leafEvent.author,
+      leafEvent.createdAt,
+      leafEvent.editedAt,
+      leafEvent.nonce,
+      Buffer.from(leafEvent.dataHash)
+    );
+    const merkleTreeAccount =
+      await ConcurrentMerkleTreeAccount.fromAccountAddress(
+        helpers.connection,
+        merkleTree.publicKey
+      );
+    const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+      [leafHash],
+      merkleTreeAccount.getMaxDepth()
+    ).getProof(0);
+    const treasuryBalance = await helpers.connection.getBalance(treasury);
+
+    const authorProgram = await helpers.getCompressionProgram(author);
+    await authorProgram.methods
+      .deleteEntry(
+        Array.from(merkleTreeAccount.getCurrentRoot()),
+        leafEvent.createdAt,
+        leafEvent.editedAt,
+        leafEvent.dataHash,
+        leafEvent.nonce,
+        leafEvent.nonce.toNumber()
+      )
+      .accounts({
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        author: leafEvent.author,
+        entryTree: null,
+        entryBond,
+        treasury,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(
+        proof.proof.map((pubkey) => ({
+          pubkey: new anchor.web3.PublicKey(pubkey),
+          isSigner: false,
+          isWritable: false,
+        }))
+      )
+      .rpc({ commitment: "confirmed" });
+
+    // Deleted within the reclaim window, so the bond goes to the treasury
+    assert.equal(await helpers.connection.getAccountInfo(entryBond), null);
+    assert.ok(
+      (await helpers.connection.getBalance(treasury)) >
+        treasuryBalance + bondAmount
+    );
+  });
+
+  it("Attaches a tree", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const newTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const maxDepth = 14;
+    const bufferSize = 64;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const allocTreeIx = await helpers.createAllocTreeIx(
+      admin.publicKey,
+      newTree.publicKey,
+      maxDepth,
+      bufferSize
+    );
+    const attachTreeIx = await program.methods
+      .attachTree(maxDepth, bufferSize)
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        newTree: newTree.publicKey,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+      })
+      .instruction();
+    await program.provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(allocTreeIx).add(attachTreeIx),
+      [newTree],
+      { commitment: "confirmed" }
+    );
+
+    const forumConfigAccount = await program.account.forumConfig.fetch(
+      forumConfigPda
+    );
+    assert.equal(forumConfigAccount.trees.length, 2);
+    assert.ok(forumConfigAccount.trees[1].merkleTree.equals(newTree.publicKey));
+    assert.equal(forumConfigAccount.totalCapacity.toNumber(), 2 << maxDepth);
+  });
+
+  it("Adds an entry to a shard", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const shardTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const shard = helpers.findShardPda(
+      merkleTree.publicKey,
+      shardTree.publicKey
+    );
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    const addShardIx = await program.methods
+      .addShard(14, 64)
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        newTree: shardTree.publicKey,
+        shard,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+      })
+      .instruction();
+    await program.provider.sendAndConfirm(
+      new anchor.web3.Transaction()
+        .add(
+          await helpers.createAllocTreeIx(
+            admin.publicKey,
+            shardTree.publicKey,
+            14,
+            64
+          )
+        )
+        .add(addShardIx),
+      [shardTree],
+      { commitment: "confirmed" }
+    );
+
+    const shardEntry = () =>
+      program.methods.addShardEntry(
+        {
+          v1: {
+            data: {
+              textPost: {
+                title: "test",
+                uri: "https://example.com",
+                flair: null,
+                nsfw: false,
+                spoiler: false,
+              },
+            },
+          },
+        },
+        [],
+        [],
+        [],
+        null
+      );
+    const shardEntryAccounts = {
+      forumConfig: forumConfigPda,
+      merkleTree: merkleTree.publicKey,
+      author: admin.publicKey,
+      sessionToken: null,
+      signer: admin.publicKey,
+      additionalSigner: null,
+      forumAdmin: null,
+      ban: helpers.findBanPda(merkleTree.publicKey, admin.publicKey),
+      authorActivity: null,
+      threadLock: null,
+      treasury: null,
+      feeMint: null,
+      feeTokenAccount: null,
+      treasuryTokenAccount: null,
+      tokenProgram: null,
+      instructions: null,
+      permitNonce: null,
+      shard,
+      tree: shardTree.publicKey,
+      parentTree: null,
+      entryBond: null,
+      logWrapper: SPL_NOOP_PROGRAM_ID,
+      compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    };
+
+    // The shard must be the one derived from the tree written to
+    try {
+      await shardEntry()
+        .accounts({ ...shardEntryAccounts, tree: merkleTree.publicKey })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "A seeds constraint was violated");
+    }
+
+    const addShardEntryIx = await shardEntry()
+      .accounts(shardEntryAccounts)
+      .instruction();
+
+    // Shard entries only read the forum config and tree, so they do not contend on them
+    for (const key of addShardEntryIx.keys) {
+      if (
+        key.pubkey.equals(forumConfigPda) ||
+        key.pubkey.equals(merkleTree.publicKey)
+      ) {
+        assert.equal(key.isWritable, false);
+      }
+    }
+
+    const leafEvent = await program.provider
+      .sendAndConfirm(new anchor.web3.Transaction().add(addShardEntryIx), [], {
+        commitment: "confirmed",
+        skipPreflight: true,
+      })
+      .then(helpers.getLeafSchemaEvent);
+
+    assert.ok(
+      leafEvent.id.equals(
+        helpers.findEntryIdPda(shardTree.publicKey, new anchor.BN(0))
+      )
+    );
+    const shardAccount = await program.account.forumShard.fetch(shard);
+    assert.equal(shardAccount.postCount.toNumber(), 1);
+    const forumConfigAccount = await program.account.forumConfig.fetch(
+      forumConfigPda
+    );
+    assert.equal(forumConfigAccount.postCount.toNumber(), 0);
+  });
+
   it("Adds a post and comment", async () => {
     const admin = anchor.web3.Keypair.generate();
     const merkleTree = anchor.web3.Keypair.generate();
@@ -42,13 +1060,454 @@ describe("Compression", () => {
         spoiler: false,
       },
     });
-    await helpers.addEntry(merkleTree.publicKey, {
-      comment: {
+    await helpers.addEntry(merkleTree.publicKey, {
+      comment: {
+        post: leafEvent.id,
+        parent: null,
+        uri: "https://example.com",
+      },
+    });
+
+    // Clients which do not pass the thread lock can still comment on unlocked posts
+    const program = await helpers.getCompressionProgram(admin);
+    await program.methods
+      .addEntry(
+        {
+          comment: {
+            post: leafEvent.id,
+            parent: null,
+            uri: "https://example.com",
+          },
+        },
+        [],
+        [],
+        []
+      )
+      .accounts({
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+        author: admin.publicKey,
+        sessionToken: null,
+        signer: admin.publicKey,
+        additionalSigner: null,
+        forumAdmin: null,
+        ban: helpers.findBanPda(merkleTree.publicKey, admin.publicKey),
+        authorActivity: null,
+        threadLock: null,
+        treasury: null,
+        feeMint: null,
+        feeTokenAccount: null,
+        treasuryTokenAccount: null,
+        tokenProgram: null,
+        instructions: null,
+        permitNonce: null,
+        activeTree: null,
+        parentTree: null,
+        entryBond: null,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("Adds a post committing to its content digest", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const body = Buffer.from("# Hello world");
+    const data: helpers.DataV2 = {
+      textPost: {
+        title: "test",
+        content: {
+          uri: "https://example.com",
+          mimeType: "text/markdown",
+          algorithm: { sha256: {} },
+          digest: Array.from(createHash("sha256").update(body).digest()),
+        },
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, { v2: data });
+
+    // The digest is part of the data hashed into the leaf
+    const program = await helpers.getCompressionProgram(admin);
+    const serializedData = program.coder.types.encode("DataV2", data);
+    assert.deepEqual(
+      Buffer.from(leafEvent.dataHash),
+      Buffer.from(keccak_256.digest(serializedData))
+    );
+  });
+
+  it("Adds an inline comment", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+    const data: helpers.DataV2 = {
+      shortComment: {
+        post: leafEvent.id,
+        parent: null,
+        depth: 1,
+        body: "gm",
+      },
+    };
+    const commentEvent = await helpers.addEntry(merkleTree.publicKey, {
+      v2: data,
+    });
+
+    const program = await helpers.getCompressionProgram(admin);
+    const serializedData = program.coder.types.encode("DataV2", data);
+    assert.deepEqual(
+      Buffer.from(commentEvent.dataHash),
+      Buffer.from(keccak_256.digest(serializedData))
+    );
+
+    // The body is logged in its own event after the leaf event
+    const [{ signature }] = await helpers.connection.getSignaturesForAddress(
+      merkleTree.publicKey,
+      { limit: 1 },
+      "confirmed"
+    );
+    const leafSchema = await helpers.getLeafSchemaEvent(signature);
+    const entryBody = await helpers.getEntryBodyEvent(signature);
+    assert.ok(leafSchema.id.equals(commentEvent.id));
+    assert.ok(entryBody.id.equals(commentEvent.id));
+    assert.equal(entryBody.body, "gm");
+  });
+
+  it("Votes on a poll", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const voter = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const data: helpers.DataV2 = {
+      pollPost: {
+        title: "test",
+        options: ["yes", "no"],
+        closesAt: null,
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.requestAirdrop(voter.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    // Polls cannot be added already closed
+    try {
+      await helpers.addEntry(merkleTree.publicKey, {
+        v2: { pollPost: { ...data.pollPost, closesAt: new anchor.BN(1) } },
+      });
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Poll is closed");
+    }
+
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, { v2: data });
+
+    const leafHash = helpers.computeCompressedEntryHash(
+      leafEvent.id,
+      leafEvent.author,
+      leafEvent.createdAt,
+      leafEvent.editedAt,
+      leafEvent.nonce,
+      Buffer.from(leafEvent.dataHash)
+    );
+    const merkleTreeAccount =
+      await ConcurrentMerkleTreeAccount.fromAccountAddress(
+        helpers.connection,
+        merkleTree.publicKey
+      );
+    const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+      [leafHash],
+      merkleTreeAccount.getMaxDepth()
+    ).getProof(0);
+
+    const program = await helpers.getCompressionProgram(voter);
+    const vote = () =>
+      program.methods
+        .votePoll(
+          {
+            root: Array.from(merkleTreeAccount.getCurrentRoot()),
+            author: leafEvent.author,
+            createdAt: leafEvent.createdAt,
+            editedAt: leafEvent.editedAt,
+            nonce: leafEvent.nonce,
+            index: leafEvent.nonce.toNumber(),
+            proofLen: proof.proof.length,
+          },
+          data,
+          1,
+          [],
+          [],
+          []
+        )
+        .accounts({
+          voter: voter.publicKey,
+          sessionToken: null,
+          signer: voter.publicKey,
+          additionalSigner: null,
+          ban: helpers.findBanPda(merkleTree.publicKey, voter.publicKey),
+          instructions: null,
+          permitNonce: null,
+          poll: leafEvent.id,
+          pollVote: helpers.findPollVotePda(leafEvent.id, voter.publicKey),
+          pollTally: helpers.findPollTallyPda(leafEvent.id),
+          forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+          merkleTree: merkleTree.publicKey,
+          entryTree: null,
+          compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .remainingAccounts(
+          proof.proof.map((pubkey) => ({
+            pubkey: new anchor.web3.PublicKey(pubkey),
+            isSigner: false,
+            isWritable: false,
+          }))
+        )
+        .rpc({ commitment: "confirmed" });
+
+    await vote();
+
+    const tally = await program.account.pollTally.fetch(
+      helpers.findPollTallyPda(leafEvent.id)
+    );
+    assert.equal(tally.votes[1].toNumber(), 1);
+
+    try {
+      await vote();
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.ok(err.logs.some((log) => log.includes("already in use")));
+    }
+  });
+
+  it("Votes on an entry", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const voter = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.requestAirdrop(voter.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+
+    const leafHash = helpers.computeCompressedEntryHash(
+      leafEvent.id,
+      leafEvent.author,
+      leafEvent.createdAt,
+      leafEvent.editedAt,
+      leafEvent.nonce,
+      Buffer.from(leafEvent.dataHash)
+    );
+    const merkleTreeAccount =
+      await ConcurrentMerkleTreeAccount.fromAccountAddress(
+        helpers.connection,
+        merkleTree.publicKey
+      );
+    const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+      [leafHash],
+      merkleTreeAccount.getMaxDepth()
+    ).getProof(0);
+
+    const program = await helpers.getCompressionProgram(voter);
+    const entryVote = helpers.findEntryVotePda(leafEvent.id, voter.publicKey);
+    const vote = (direction: helpers.VoteDirection) =>
+      program.methods
+        .voteEntry(
+          {
+            root: Array.from(merkleTreeAccount.getCurrentRoot()),
+            author: leafEvent.author,
+            createdAt: leafEvent.createdAt,
+            editedAt: leafEvent.editedAt,
+            nonce: leafEvent.nonce,
+            index: leafEvent.nonce.toNumber(),
+            proofLen: proof.proof.length,
+          },
+          leafEvent.dataHash,
+          direction,
+          [],
+          [],
+          []
+        )
+        .accounts({
+          voter: voter.publicKey,
+          sessionToken: null,
+          signer: voter.publicKey,
+          additionalSigner: null,
+          ban: helpers.findBanPda(merkleTree.publicKey, voter.publicKey),
+          instructions: null,
+          permitNonce: null,
+          entryId: leafEvent.id,
+          entryVote,
+          forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+          merkleTree: merkleTree.publicKey,
+          entryTree: null,
+          compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .remainingAccounts(
+          proof.proof.map((pubkey) => ({
+            pubkey: new anchor.web3.PublicKey(pubkey),
+            isSigner: false,
+            isWritable: false,
+          }))
+        )
+        .rpc({ commitment: "confirmed" });
+
+    await vote({ up: {} });
+
+    try {
+      await vote({ up: {} });
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Already voted");
+    }
+
+    await vote({ down: {} });
+    const voteAccount = await program.account.entryVote.fetch(entryVote);
+    assert.deepEqual(voteAccount.direction, { down: {} });
+  });
+
+  it("Requires a parent proof for strict comments", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const commenter = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const post: helpers.DataV1 = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+
+    const program = await helpers.getCompressionProgram(admin);
+    await program.methods
+      .setStrictComments({ maxDepth: 2 })
+      .accounts({
+        admin: admin.publicKey,
+        payer: admin.publicKey,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, post);
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, {
+        comment: {
+          post: leafEvent.id,
+          parent: null,
+          uri: "https://example.com",
+        },
+      });
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Comments require a parent proof");
+    }
+
+    const leafHash = helpers.computeCompressedEntryHash(
+      leafEvent.id,
+      leafEvent.author,
+      leafEvent.createdAt,
+      leafEvent.editedAt,
+      leafEvent.nonce,
+      Buffer.from(leafEvent.dataHash)
+    );
+    const merkleTreeAccount =
+      await ConcurrentMerkleTreeAccount.fromAccountAddress(
+        helpers.connection,
+        merkleTree.publicKey
+      );
+    const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+      [leafHash],
+      merkleTreeAccount.getMaxDepth()
+    ).getProof(0);
+
+    const comment: helpers.DataV2 = {
+      shortComment: {
         post: leafEvent.id,
         parent: null,
-        uri: "https://example.com",
+        depth: 1,
+        body: "gm",
       },
-    });
+    };
+    const commentEvent = await helpers.addEntry(
+      merkleTree.publicKey,
+      { v2: comment },
+      commenter,
+      [],
+      {
+        entry: {
+          leaf: {
+            root: Array.from(merkleTreeAccount.getCurrentRoot()),
+            author: leafEvent.author,
+            createdAt: leafEvent.createdAt,
+            editedAt: leafEvent.editedAt,
+            nonce: leafEvent.nonce,
+            index: leafEvent.nonce.toNumber(),
+            proofLen: proof.proof.length,
+          },
+          data: { v1: { data: post } },
+        },
+        proof: proof.proof.map((pubkey) => new anchor.web3.PublicKey(pubkey)),
+      }
+    );
+
+    // Edits cannot move the comment to a post that was never verified
+    try {
+      await helpers.editEntry(
+        merkleTree.publicKey,
+        commenter,
+        [leafEvent, commentEvent],
+        1,
+        { v2: { data: comment } },
+        {
+          v2: {
+            shortComment: {
+              ...comment.shortComment,
+              post: anchor.web3.Keypair.generate().publicKey,
+            },
+          },
+        }
+      );
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(
+        err.error.errorMessage,
+        "Edits cannot change the entry type or comment target"
+      );
+    }
   });
 
   it("Gates entry to an spl-token", async () => {
@@ -64,7 +1523,9 @@ describe("Compression", () => {
       0
     );
     const gate: helpers.Gate = {
-      amount: new anchor.BN(1),
+      minAmount: new anchor.BN(1),
+      maxAmount: null,
+      decimals: null,
       address: [mintAddress],
       ruleType: {
         token: {},
@@ -123,9 +1584,141 @@ describe("Compression", () => {
         },
       },
       admin,
-      mintAddress,
-      tokenAccount.address
+      [{ gate: 0, accounts: [mintAddress, tokenAccount.address] }]
+    );
+  });
+
+  it("Gates entry to multiple spl-tokens", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    const mints: anchor.web3.PublicKey[] = [];
+    const tokenAccounts: anchor.web3.PublicKey[] = [];
+
+    for (let i = 0; i < 2; i++) {
+      const mintAddress = await splToken.createMint(
+        helpers.connection,
+        admin,
+        admin.publicKey,
+        admin.publicKey,
+        0
+      );
+      const tokenAccount = await splToken.getOrCreateAssociatedTokenAccount(
+        helpers.connection,
+        admin,
+        mintAddress,
+        admin.publicKey
+      );
+      await splToken.mintTo(
+        helpers.connection,
+        admin,
+        mintAddress,
+        tokenAccount.address,
+        admin.publicKey,
+        1
+      );
+      mints.push(mintAddress);
+      tokenAccounts.push(tokenAccount.address);
+    }
+
+    const gates: helpers.Gate[] = mints.map((mintAddress) => ({
+      minAmount: new anchor.BN(1),
+      maxAmount: null,
+      decimals: null,
+      address: [mintAddress],
+      ruleType: {
+        token: {},
+      },
+      operator: {
+        // @ts-ignore
+        and: {},
+      },
+    }));
+    await helpers.initForum(admin, merkleTree, undefined, gates);
+
+    const data: helpers.DataV1 = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+
+    try {
+      await helpers.addEntry(merkleTree.publicKey, data, admin, [
+        { gate: 0, accounts: [mints[0], tokenAccounts[0]] },
+      ]);
+      /// Expecting this to fail because only one of the gates is satisfied
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Unauthorized");
+    }
+
+    await helpers.addEntry(merkleTree.publicKey, data, admin, [
+      { gate: 0, accounts: [mints[0], tokenAccounts[0]] },
+      { gate: 1, accounts: [mints[1], tokenAccounts[1]] },
+    ]);
+  });
+
+  it("Gates entry to a Token-2022 collection group", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+
+    await helpers.requestAirdrop(admin.publicKey);
+    const group = await helpers.createToken2022Group(admin);
+    const otherGroup = await helpers.createToken2022Group(admin);
+    const gate: helpers.Gate = {
+      minAmount: new anchor.BN(1),
+      maxAmount: null,
+      decimals: null,
+      address: [group],
+      ruleType: {
+        nft: {},
+      },
+      operator: {
+        // @ts-ignore
+        or: {},
+      },
+    };
+    await helpers.initForum(admin, merkleTree, undefined, [gate]);
+
+    const data = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+    // Members carry their metadata in the mint, so it is passed as the metadata account
+    const credential = ({ mint, tokenAccount }) => ({
+      gate: 0,
+      accounts: [mint, tokenAccount, mint],
+    });
+
+    const otherMember = await helpers.createToken2022GroupMember(
+      admin,
+      otherGroup
     );
+    try {
+      await helpers.addEntry(merkleTree.publicKey, data, admin, [
+        credential(otherMember),
+      ]);
+      /// Expecting this to fail because the nft is a member of another group
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Unauthorized");
+    }
+
+    const member = await helpers.createToken2022GroupMember(admin, group);
+    /// Expecting this to succeed now because the nft is a member of the gate group
+    await helpers.addEntry(merkleTree.publicKey, data, admin, [
+      credential(member),
+    ]);
   });
 
   it("Verifies an entry", async () => {
@@ -234,6 +1827,9 @@ describe("Compression", () => {
           forumConfig: forumConfigPda,
           merkleTree: merkleTree.publicKey,
           author: leafEvent.author,
+          entryTree: null,
+          entryBond: helpers.findEntryBondPda(leafEvent.id),
+          treasury: null,
           logWrapper: SPL_NOOP_PROGRAM_ID,
           compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -259,4 +1855,98 @@ describe("Compression", () => {
       throw err;
     }
   });
+
+  it("Edits an entry", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const author = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    const post: helpers.DataV1 = {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    };
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, post, author);
+
+    const leafHash = helpers.computeCompressedEntryHash(
+      leafEvent.id,
+      leafEvent.author,
+      leafEvent.createdAt,
+      leafEvent.editedAt,
+      leafEvent.nonce,
+      Buffer.from(leafEvent.dataHash)
+    );
+    const merkleTreeAccount =
+      await ConcurrentMerkleTreeAccount.fromAccountAddress(
+        helpers.connection,
+        merkleTree.publicKey
+      );
+    const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+      [leafHash],
+      merkleTreeAccount.getMaxDepth()
+    ).getProof(0);
+
+    const program = await helpers.getCompressionProgram(author);
+    const signature = await program.methods
+      .editEntry(
+        Array.from(merkleTreeAccount.getCurrentRoot()),
+        leafEvent.createdAt,
+        leafEvent.editedAt,
+        { v1: { data: post } },
+        leafEvent.nonce,
+        leafEvent.nonce.toNumber(),
+        {
+          textPost: {
+            title: "test edited",
+            uri: "https://example.com/edited",
+            flair: "test",
+            nsfw: false,
+            spoiler: false,
+          },
+        },
+        [],
+        [],
+        []
+      )
+      .accounts({
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        entryTree: null,
+        threadLock: null,
+        instructions: null,
+        permitNonce: null,
+        author: author.publicKey,
+        sessionToken: null,
+        signer: author.publicKey,
+        additionalSigner: null,
+        forumAdmin: null,
+        ban: helpers.findBanPda(merkleTree.publicKey, author.publicKey),
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(
+        proof.proof.map((pubkey) => ({
+          pubkey: new anchor.web3.PublicKey(pubkey),
+          isSigner: false,
+          isWritable: false,
+        }))
+      )
+      .rpc({
+        commitment: "confirmed",
+        skipPreflight: true,
+      });
+
+    const editedEvent = await helpers.getLeafSchemaEvent(signature);
+    assert.ok(editedEvent.id.equals(leafEvent.id), "entry.id");
+    assert.ok(editedEvent.createdAt.eq(leafEvent.createdAt), "entry.createdAt");
+    assert.ok(editedEvent.editedAt !== null, "entry.editedAt");
+  });
 });
@@ -3,11 +3,19 @@ import { keypairIdentity, Metaplex } from "@metaplex-foundation/js";
 import { PROGRAM_ID as METADATA_PROGRAM_ID } from "@metaplex-foundation/mpl-token-metadata";
 import { PROGRAM_ID as BUBBLEGUM_PROGRAM_ID } from "@metaplex-foundation/mpl-bubblegum";
 import {
+  ConcurrentMerkleTreeAccount,
   getConcurrentMerkleTreeAccountSize,
+  MerkleTree,
   SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
   SPL_NOOP_PROGRAM_ID,
 } from "@solana/spl-account-compression";
+import {
+  createAssociatedTokenAccountInstruction,
+  createMintToInstruction,
+  getAssociatedTokenAddress,
+} from "@solana/spl-token";
 import base58 from "bs58";
+import { createHash } from "crypto";
 import { keccak_256 } from "js-sha3";
 
 import {
@@ -35,15 +43,45 @@ type SnakeToCamelCaseObj<T> = T extends object
 type OndaCompressionTypes = anchor.IdlTypes<OndaCompression>;
 type OndaAwardTypes = anchor.IdlTypes<OndaAwards>;
 export type DataV1 = OndaCompressionTypes["DataV1"];
+export type DataV2 = OndaCompressionTypes["DataV2"];
+export type VoteDirection = OndaCompressionTypes["VoteDirection"];
+export type ParentEntry = OndaCompressionTypes["ParentEntry"];
+export type EntryData = OndaCompressionTypes["EntryData"];
 export type LeafSchemaV1 = SnakeToCamelCaseObj<
   OndaCompressionTypes["LeafSchema"]["v1"]
 >;
 export type Gate = OndaCompressionTypes["Gate"];
+export type PolicyNode = OndaCompressionTypes["PolicyNode"];
+/**
+ * Accounts proving a gate, in the order expected by the program:
+ * [mint, tokenAccount] for token gates, [mint, tokenAccount, metadata] for nft gates
+ */
+export type GateCredential = {
+  gate: number;
+  accounts: anchor.web3.PublicKey[];
+};
+
+/**
+ * The forum config wrappers of the moderation program take argument types defined by the
+ * compression program, which are not part of the moderation IDL
+ */
+const MODERATION_IDL_WITH_TYPES = {
+  ...MODERATION_IDL,
+  types: [
+    ...MODERATION_IDL.types,
+    ...COMPRESSION_IDL.types.filter(
+      ({ name }) => !MODERATION_IDL.types.some((type) => type.name === name)
+    ),
+  ],
+} as unknown as OndaModeration;
 
 export const compressionProgram = anchor.workspace
   .OndaCompression as anchor.Program<OndaCompression>;
-export const moderationProgram = anchor.workspace
-  .OndaModeration as anchor.Program<OndaModeration>;
+export const moderationProgram = new anchor.Program<OndaModeration>(
+  MODERATION_IDL_WITH_TYPES,
+  "5o1PS9vW57YUezjCuN7aycLnACKkaxGyb7Ak7pdcK6sw",
+  compressionProgram.provider
+);
 export const namespaceProgram = anchor.workspace
   .OndaNamespace as anchor.Program<OndaNamespace>;
 export const awardsProgram = anchor.workspace
@@ -82,7 +120,7 @@ export async function getModerationProgram(
   keypair: anchor.web3.Keypair = anchor.web3.Keypair.generate()
 ) {
   return new anchor.Program<OndaModeration>(
-    MODERATION_IDL,
+    MODERATION_IDL_WITH_TYPES,
     moderationProgram.programId,
     new anchor.AnchorProvider(
       connection,
@@ -127,6 +165,104 @@ export function findForumConfigPda(merkleTree: anchor.web3.PublicKey) {
   )[0];
 }
 
+export function findBanPda(
+  merkleTree: anchor.web3.PublicKey,
+  author: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("ban"), merkleTree.toBuffer(), author.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findAuthorActivityPda(
+  merkleTree: anchor.web3.PublicKey,
+  author: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("activity"), merkleTree.toBuffer(), author.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findShardPda(
+  merkleTree: anchor.web3.PublicKey,
+  tree: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("shard"), merkleTree.toBuffer(), tree.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findTreasuryPda(merkleTree: anchor.web3.PublicKey) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury"), merkleTree.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findEntryIdPda(
+  merkleTree: anchor.web3.PublicKey,
+  nonce: anchor.BN
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("entry"), merkleTree.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findForumPinsPda(merkleTree: anchor.web3.PublicKey) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("pins"), merkleTree.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findThreadLockPda(
+  merkleTree: anchor.web3.PublicKey,
+  post: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("lock"), merkleTree.toBuffer(), post.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findEntryBondPda(entryId: anchor.web3.PublicKey) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("bond"), entryId.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findPollVotePda(
+  poll: anchor.web3.PublicKey,
+  voter: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("poll_vote"), poll.toBuffer(), voter.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findPollTallyPda(poll: anchor.web3.PublicKey) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("poll_tally"), poll.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
+export function findEntryVotePda(
+  entryId: anchor.web3.PublicKey,
+  voter: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("entry_vote"), entryId.toBuffer(), voter.toBuffer()],
+    compressionProgram.programId
+  )[0];
+}
+
 export function findTeamPda(merkleTree: anchor.web3.PublicKey) {
   return anchor.web3.PublicKey.findProgramAddressSync(
     [Buffer.from("team"), merkleTree.toBuffer()],
@@ -179,16 +315,12 @@ export function findBubblegumSignerPda() {
   )[0];
 }
 
-export async function initForum(
-  admin: anchor.web3.Keypair,
-  merkleTree: anchor.web3.Keypair,
-  flair: string[] = ["test", "test2"],
-  gates: Gate[] = null
+export async function createAllocTreeIx(
+  payer: anchor.web3.PublicKey,
+  merkleTree: anchor.web3.PublicKey,
+  maxDepth: number,
+  bufferSize: number
 ) {
-  const program = await getCompressionProgram(admin);
-  const forumConfig = findForumConfigPda(merkleTree.publicKey);
-  const maxDepth = 14;
-  const bufferSize = 64;
   const canopyDepth = maxDepth - 3;
   const space = getConcurrentMerkleTreeAccountSize(
     maxDepth,
@@ -196,16 +328,36 @@ export async function initForum(
     canopyDepth
   );
   const lamports = await connection.getMinimumBalanceForRentExemption(space);
-  const allocTreeIx = anchor.web3.SystemProgram.createAccount({
+
+  return anchor.web3.SystemProgram.createAccount({
     lamports,
     space: space,
-    fromPubkey: admin.publicKey,
-    newAccountPubkey: merkleTree.publicKey,
+    fromPubkey: payer,
+    newAccountPubkey: merkleTree,
     programId: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
   });
+}
+
+export async function initForum(
+  admin: anchor.web3.Keypair,
+  merkleTree: anchor.web3.Keypair,
+  flair: string[] = ["test", "test2"],
+  gates: Gate[] = null,
+  policy: PolicyNode[] = null
+) {
+  const program = await getCompressionProgram(admin);
+  const forumConfig = findForumConfigPda(merkleTree.publicKey);
+  const maxDepth = 14;
+  const bufferSize = 64;
+  const allocTreeIx = await createAllocTreeIx(
+    admin.publicKey,
+    merkleTree.publicKey,
+    maxDepth,
+    bufferSize
+  );
 
   const initForumIx = await program.methods
-    .initForum(maxDepth, bufferSize, flair, gates)
+    .initForum(maxDepth, bufferSize, flair, gates, policy)
     .accounts({
       payer: admin.publicKey,
       forumConfig,
@@ -230,53 +382,232 @@ export async function initForum(
 
 export async function addEntry(
   merkleTree: anchor.web3.PublicKey,
-  data: DataV1,
+  data: DataV1 | { v2: DataV2 },
   author: anchor.web3.Keypair = anchor.web3.Keypair.generate(),
-  mint: anchor.web3.PublicKey = null,
-  tokenAccount: anchor.web3.PublicKey = null,
-  metadata: anchor.web3.PublicKey = null
+  credentials: GateCredential[] = [],
+  // Required for comments in forums with strict comments
+  parent: { entry: ParentEntry; proof: anchor.web3.PublicKey[] } = null,
+  // Co-signs the entry for gates with an additional signer rule
+  additionalSigner: anchor.web3.Keypair = null,
+  // Required for announcement flair
+  forumAdmin: anchor.web3.Keypair = null
 ): Promise<LeafSchemaV1> {
   const program = await getCompressionProgram(author);
   const forumConfig = findForumConfigPda(merkleTree);
   await requestAirdrop(author.publicKey);
+  const forumConfigAccount = await program.account.forumConfig.fetch(
+    forumConfig
+  );
 
-  return program.methods
-    .addEntry(data)
+  const entryId = findEntryIdPda(merkleTree, forumConfigAccount.postCount);
+
+  const comment =
+    "v2" in data
+      ? data.v2.comment ?? data.v2.shortComment
+      : data.comment;
+
+  const gates = credentials.map(({ gate }) => ({ gate }));
+  const addEntryIx = await ("v2" in data
+    ? program.methods.addEntryV2(
+        data.v2,
+        gates,
+        [],
+        [],
+        parent ? parent.entry : null
+      )
+    : program.methods.addEntry(data, gates, [], [])
+  )
     .accounts({
       forumConfig,
       merkleTree,
-      mint,
-      tokenAccount,
-      metadata,
       author: program.provider.publicKey,
       sessionToken: null,
       signer: program.provider.publicKey,
+      additionalSigner: additionalSigner ? additionalSigner.publicKey : null,
+      forumAdmin: forumAdmin ? forumAdmin.publicKey : null,
+      ban: findBanPda(merkleTree, author.publicKey),
+      authorActivity: forumConfigAccount.slowMode
+        ? findAuthorActivityPda(merkleTree, author.publicKey)
+        : null,
+      threadLock: comment ? findThreadLockPda(merkleTree, comment.post) : null,
+      treasury: forumConfigAccount.postingFee
+        ? findTreasuryPda(merkleTree)
+        : null,
+      feeMint: null,
+      feeTokenAccount: null,
+      treasuryTokenAccount: null,
+      tokenProgram: null,
+      instructions: null,
+      permitNonce: null,
+      activeTree: null,
+      parentTree: null,
+      entryBond: forumConfigAccount.bondMode ? findEntryBondPda(entryId) : null,
+      logWrapper: SPL_NOOP_PROGRAM_ID,
+      compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    })
+    .remainingAccounts(
+      credentials
+        .flatMap(({ accounts }) => accounts)
+        .concat(parent ? parent.proof : [])
+        .map((pubkey) => ({
+          pubkey,
+          isSigner: false,
+          isWritable: false,
+        }))
+    )
+    .instruction();
+
+  // The additional signer is an unchecked account in the idl
+  for (const key of addEntryIx.keys) {
+    if (additionalSigner && key.pubkey.equals(additionalSigner.publicKey)) {
+      key.isSigner = true;
+    }
+  }
+
+  return program.provider
+    .sendAndConfirm(
+      new anchor.web3.Transaction().add(addEntryIx),
+      [additionalSigner, forumAdmin].filter((signer) => signer !== null),
+      {
+        commitment: "confirmed",
+        skipPreflight: true,
+      }
+    )
+    .then(getLeafSchemaEvent);
+}
+
+// Edits the entry at `index` among `leafEvents`, every entry added to the tree so far
+export async function editEntry(
+  merkleTree: anchor.web3.PublicKey,
+  author: anchor.web3.Keypair,
+  leafEvents: LeafSchemaV1[],
+  index: number,
+  previousData: EntryData,
+  data: DataV1 | { v2: DataV2 },
+  // Only required to change to flair with a policy
+  credentials: GateCredential[] = []
+): Promise<LeafSchemaV1> {
+  const program = await getCompressionProgram(author);
+  const leafEvent = leafEvents[index];
+  const comment =
+    "v2" in data
+      ? data.v2.comment ?? data.v2.shortComment
+      : data.comment;
+  const merkleTreeAccount = await ConcurrentMerkleTreeAccount.fromAccountAddress(
+    connection,
+    merkleTree
+  );
+  const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+    leafEvents.map((event) =>
+      computeCompressedEntryHash(
+        event.id,
+        event.author,
+        event.createdAt,
+        event.editedAt,
+        event.nonce,
+        Buffer.from(event.dataHash)
+      )
+    ),
+    merkleTreeAccount.getMaxDepth()
+  ).getProof(index);
+
+  const args = [
+    Array.from(merkleTreeAccount.getCurrentRoot()),
+    leafEvent.createdAt,
+    leafEvent.editedAt,
+    previousData,
+    leafEvent.nonce,
+    leafEvent.nonce.toNumber(),
+  ] as const;
+  const gates = credentials.map(({ gate }) => ({ gate }));
+
+  return ("v2" in data
+    ? program.methods.editEntryV2(...args, data.v2, gates, [], [])
+    : program.methods.editEntry(...args, data, gates, [], [])
+  )
+    .accounts({
+      forumConfig: findForumConfigPda(merkleTree),
+      merkleTree,
+      entryTree: null,
+      threadLock: comment ? findThreadLockPda(merkleTree, comment.post) : null,
+      instructions: null,
+      permitNonce: null,
+      author: author.publicKey,
+      sessionToken: null,
+      signer: author.publicKey,
       additionalSigner: null,
+      forumAdmin: null,
+      ban: findBanPda(merkleTree, author.publicKey),
       logWrapper: SPL_NOOP_PROGRAM_ID,
       compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
     })
+    .remainingAccounts(
+      credentials
+        .flatMap(({ accounts }) => accounts)
+        .concat(proof.proof.map((pubkey) => new anchor.web3.PublicKey(pubkey)))
+        .map((pubkey) => ({
+          pubkey,
+          isSigner: false,
+          isWritable: false,
+        }))
+    )
     .rpc({ commitment: "confirmed", skipPreflight: true })
-    .then(async (signature) => {
-      const parsedTx = await program.provider.connection.getParsedTransaction(
-        signature,
-        "confirmed"
-      );
-      const innerInstructions = parsedTx.meta.innerInstructions[0];
-      const noopIx = innerInstructions.instructions[0];
-
-      let leafSchema: LeafSchemaV1;
-
-      if ("data" in noopIx) {
-        const serializedEvent = noopIx.data;
-        const event = base58.decode(serializedEvent);
-        const eventBuffer = Buffer.from(event.slice(8));
-        leafSchema = program.coder.types.decode("LeafSchema", eventBuffer).v1;
-      } else {
+    .then(getLeafSchemaEvent);
+}
+
+/**
+ * Application data the compression program logged through the noop program, in order. Each
+ * entry logs a `LeafSchemaEvent`, followed by an `EntryBodyEvent` when it has an inline body.
+ */
+export async function getApplicationData(signature: string): Promise<Buffer[]> {
+  const parsedTx = await connection.getParsedTransaction(
+    signature,
+    "confirmed"
+  );
+  const innerInstructions = parsedTx.meta.innerInstructions[0];
+
+  return (
+    innerInstructions.instructions
+      // Account creation and fee transfers can also appear as inner instructions
+      .filter((ix) => ix.programId.equals(SPL_NOOP_PROGRAM_ID))
+      .map((ix) => {
+        if ("data" in ix) {
+          return Buffer.from(base58.decode(ix.data));
+        }
         throw new Error("No data in noopIx");
-      }
+      })
+      // Skip the changelogs of the account compression program, only application data is
+      // prefixed by the `ApplicationData` event tag, followed by the version and data length
+      .filter((event) => event[0] === 1)
+      .map((event) => event.subarray(6))
+  );
+}
 
-      return leafSchema;
-    });
+export async function getLeafSchemaEvent(
+  signature: string
+): Promise<LeafSchemaV1> {
+  const [leafSchemaEvent] = await getApplicationData(signature);
+  // Skip the event type and version
+  return compressionProgram.coder.types.decode(
+    "LeafSchema",
+    leafSchemaEvent.subarray(2)
+  ).v1;
+}
+
+export async function getEntryBodyEvent(
+  signature: string
+): Promise<{ id: anchor.web3.PublicKey; body: string } | null> {
+  const [, entryBodyEvent] = await getApplicationData(signature);
+
+  if (entryBodyEvent === undefined) {
+    return null;
+  }
+
+  return compressionProgram.coder.types.decode(
+    "EntryBodyEvent",
+    entryBodyEvent
+  );
 }
 
 export async function initTeam(
@@ -417,3 +748,162 @@ export async function createAward(
     merkleTree: merkleTree.publicKey,
   };
 }
+
+export const TOKEN_2022_PROGRAM_ID = new anchor.web3.PublicKey(
+  "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"
+);
+
+// Base mint padded to the size of a token account, then the account type
+const TOKEN_2022_MINT_BASE_SIZE = 166;
+const TOKEN_2022_POINTER_SIZE = 4 + 64;
+const TOKEN_GROUP_SIZE = 80;
+const TOKEN_GROUP_MEMBER_SIZE = 72;
+
+function getInterfaceDiscriminator(name: string) {
+  return createHash("sha256").update(name).digest().subarray(0, 8);
+}
+
+async function createToken2022Mint(
+  authority: anchor.web3.Keypair,
+  pointerInstruction: number,
+  extensionSize: number,
+  initExtensionIx: (
+    mint: anchor.web3.PublicKey
+  ) => anchor.web3.TransactionInstruction
+) {
+  const mint = anchor.web3.Keypair.generate();
+  const space = TOKEN_2022_MINT_BASE_SIZE + TOKEN_2022_POINTER_SIZE;
+  // The extension initialized last reallocates the mint, so it must be funded up front
+  const lamports = await connection.getMinimumBalanceForRentExemption(
+    space + 4 + extensionSize
+  );
+  const keys = [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }];
+
+  const tx = new anchor.web3.Transaction()
+    .add(
+      anchor.web3.SystemProgram.createAccount({
+        lamports,
+        space,
+        fromPubkey: authority.publicKey,
+        newAccountPubkey: mint.publicKey,
+        programId: TOKEN_2022_PROGRAM_ID,
+      })
+    )
+    .add(
+      // Initialize the pointer to the mint itself
+      new anchor.web3.TransactionInstruction({
+        keys,
+        programId: TOKEN_2022_PROGRAM_ID,
+        data: Buffer.concat([
+          Buffer.from([pointerInstruction, 0]),
+          authority.publicKey.toBuffer(),
+          mint.publicKey.toBuffer(),
+        ]),
+      })
+    )
+    .add(
+      // InitializeMint2 with no decimals and no freeze authority
+      new anchor.web3.TransactionInstruction({
+        keys,
+        programId: TOKEN_2022_PROGRAM_ID,
+        data: Buffer.concat([
+          Buffer.from([20, 0]),
+          authority.publicKey.toBuffer(),
+          Buffer.from([0]),
+        ]),
+      })
+    )
+    .add(initExtensionIx(mint.publicKey));
+
+  await anchor.web3.sendAndConfirmTransaction(
+    connection,
+    tx,
+    [authority, mint],
+    { commitment: "confirmed" }
+  );
+
+  return mint.publicKey;
+}
+
+export async function createToken2022Group(authority: anchor.web3.Keypair) {
+  const maxSize = Buffer.alloc(8);
+  maxSize.writeBigUInt64LE(BigInt(100));
+
+  return createToken2022Mint(authority, 40, TOKEN_GROUP_SIZE, (mint) => {
+    return new anchor.web3.TransactionInstruction({
+      keys: [
+        { pubkey: mint, isSigner: false, isWritable: true },
+        { pubkey: mint, isSigner: false, isWritable: false },
+        { pubkey: authority.publicKey, isSigner: true, isWritable: false },
+      ],
+      programId: TOKEN_2022_PROGRAM_ID,
+      data: Buffer.concat([
+        getInterfaceDiscriminator(
+          "spl_token_group_interface:initialize_token_group"
+        ),
+        authority.publicKey.toBuffer(),
+        maxSize,
+      ]),
+    });
+  });
+}
+
+export async function createToken2022GroupMember(
+  authority: anchor.web3.Keypair,
+  group: anchor.web3.PublicKey
+) {
+  const mint = await createToken2022Mint(
+    authority,
+    41,
+    TOKEN_GROUP_MEMBER_SIZE,
+    (mint) => {
+      return new anchor.web3.TransactionInstruction({
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: authority.publicKey, isSigner: true, isWritable: false },
+          { pubkey: group, isSigner: false, isWritable: true },
+          { pubkey: authority.publicKey, isSigner: true, isWritable: false },
+        ],
+        programId: TOKEN_2022_PROGRAM_ID,
+        data: getInterfaceDiscriminator(
+          "spl_token_group_interface:initialize_member"
+        ),
+      });
+    }
+  );
+  const tokenAccount = await getAssociatedTokenAddress(
+    mint,
+    authority.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+
+  await anchor.web3.sendAndConfirmTransaction(
+    connection,
+    new anchor.web3.Transaction()
+      .add(
+        createAssociatedTokenAccountInstruction(
+          authority.publicKey,
+          tokenAccount,
+          authority.publicKey,
+          mint,
+          TOKEN_2022_PROGRAM_ID
+        )
+      )
+      .add(
+        createMintToInstruction(
+          mint,
+          tokenAccount,
+          authority.publicKey,
+          1,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+    [authority],
+    { commitment: "confirmed" }
+  );
+
+  return { mint, tokenAccount };
+}
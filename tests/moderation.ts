@@ -159,6 +159,9 @@ describe("Moderation", () => {
         author: leafEvent.author,
         forumConfig: forumConfigPda,
         merkleTree: merkleTree.publicKey,
+        entryTree: null,
+        entryBond: helpers.findEntryBondPda(leafEvent.id),
+        treasury: null,
         logWrapper: SPL_NOOP_PROGRAM_ID,
         ondaCompression: helpers.compressionProgram.programId,
         compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
@@ -175,4 +178,558 @@ describe("Moderation", () => {
         skipPreflight: true,
       });
   });
+
+  it("forfeits the bond of an entry deleted by a member", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const teamPda = helpers.findTeamPda(merkleTree.publicKey);
+    const treasury = helpers.findTreasuryPda(merkleTree.publicKey);
+    const bondAmount = anchor.web3.LAMPORTS_PER_SOL / 10;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setBondMode({
+        amount: new anchor.BN(bondAmount),
+        reclaimWindow: new anchor.BN(3600),
+      })
+      .accounts({
+        member: admin.publicKey,
+        team: teamPda,
+        forumConfig: forumConfigPda,
+        treasury,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+    const entryBond = helpers.findEntryBondPda(leafEvent.id);
+
+    const leafHash = helpers.computeCompressedEntryHash(
+      leafEvent.id,
+      leafEvent.author,
+      leafEvent.createdAt,
+      leafEvent.editedAt,
+      leafEvent.nonce,
+      Buffer.from(leafEvent.dataHash)
+    );
+    const merkleTreeAccount =
+      await ConcurrentMerkleTreeAccount.fromAccountAddress(
+        helpers.connection,
+        merkleTree.publicKey
+      );
+    const proof = MerkleTree.sparseMerkleTreeFromLeaves(
+      [leafHash],
+      merkleTreeAccount.getMaxDepth()
+    ).getProof(0);
+
+    const balanceBefore = await helpers.connection.getBalance(treasury);
+    const bondBalance = await helpers.connection.getBalance(entryBond);
+    await moderationProgram.methods
+      .deleteEntry(
+        Array.from(merkleTreeAccount.getCurrentRoot()),
+        leafEvent.createdAt,
+        leafEvent.editedAt,
+        leafEvent.dataHash,
+        leafEvent.nonce,
+        leafEvent.nonce.toNumber()
+      )
+      .accounts({
+        member: admin.publicKey,
+        team: teamPda,
+        author: leafEvent.author,
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        entryTree: null,
+        entryBond,
+        treasury,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        ondaCompression: helpers.compressionProgram.programId,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts(
+        proof.proof.map((pubkey) => ({
+          pubkey: new anchor.web3.PublicKey(pubkey),
+          isSigner: false,
+          isWritable: false,
+        }))
+      )
+      .rpc({ commitment: "confirmed" });
+
+    assert.equal(await helpers.connection.getAccountInfo(entryBond), null);
+    assert.equal(
+      (await helpers.connection.getBalance(treasury)) - balanceBefore,
+      bondBalance
+    );
+  });
+
+  it("sets gates as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const moderator = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const teamPda = helpers.findTeamPda(merkleTree.publicKey);
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const gate: helpers.Gate = {
+      minAmount: new anchor.BN(1),
+      maxAmount: null,
+      decimals: null,
+      address: [anchor.web3.Keypair.generate().publicKey],
+      ruleType: {
+        additionalSigner: {},
+      },
+      operator: {
+        // @ts-ignore
+        or: {},
+      },
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.requestAirdrop(moderator.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .addMember({ moderator: {} })
+      .accounts({
+        admin: admin.publicKey,
+        newMember: moderator.publicKey,
+        team: teamPda,
+        merkleTree: merkleTree.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const accounts = {
+      team: teamPda,
+      forumConfig: forumConfigPda,
+      merkleTree: merkleTree.publicKey,
+      ondaCompression: helpers.compressionProgram.programId,
+    };
+
+    try {
+      const moderatorProgram = await helpers.getModerationProgram(moderator);
+      await moderatorProgram.methods
+        .setGates([gate], null)
+        .accounts({ ...accounts, member: moderator.publicKey })
+        .rpc({ commitment: "confirmed" });
+      assert.fail("Should have failed");
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Unauthorized.");
+    }
+
+    await moderationProgram.methods
+      .setGates([gate], null)
+      .accounts({ ...accounts, member: admin.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const forumConfigAccount =
+      await helpers.compressionProgram.account.forumConfig.fetch(
+        forumConfigPda
+      );
+    assert.equal(forumConfigAccount.gate.length, 1);
+    assert.ok(forumConfigAccount.gate[0].ruleType.additionalSigner);
+  });
+
+  it("sets slow mode as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setSlowMode({
+        minPostInterval: 3600,
+        minCommentInterval: 60,
+        maxEntriesPerWindow: 10,
+      })
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const forumConfigAccount =
+      await helpers.compressionProgram.account.forumConfig.fetch(
+        forumConfigPda
+      );
+    assert.equal(forumConfigAccount.slowMode.minPostInterval, 3600);
+    assert.equal(forumConfigAccount.slowMode.minCommentInterval, 60);
+    assert.equal(forumConfigAccount.slowMode.maxEntriesPerWindow, 10);
+  });
+
+  it("sets a posting fee as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const treasury = helpers.findTreasuryPda(merkleTree.publicKey);
+    const postAmount = anchor.web3.LAMPORTS_PER_SOL / 100;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setPostingFee({
+        mint: null,
+        postAmount: new anchor.BN(postAmount),
+        commentAmount: new anchor.BN(0),
+      })
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumConfig: forumConfigPda,
+        treasury,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const balanceBefore = await helpers.connection.getBalance(treasury);
+    await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+    const balanceAfter = await helpers.connection.getBalance(treasury);
+    assert.equal(balanceAfter - balanceBefore, postAmount);
+  });
+
+  it("sets bond mode as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const bondAmount = anchor.web3.LAMPORTS_PER_SOL / 10;
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setBondMode({
+        amount: new anchor.BN(bondAmount),
+        reclaimWindow: new anchor.BN(3600),
+      })
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumConfig: forumConfigPda,
+        treasury: helpers.findTreasuryPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await helpers.addEntry(merkleTree.publicKey, {
+      textPost: {
+        title: "test",
+        uri: "https://example.com",
+        flair: null,
+        nsfw: false,
+        spoiler: false,
+      },
+    });
+    const entryBondAccount =
+      await helpers.compressionProgram.account.entryBond.fetch(
+        helpers.findEntryBondPda(leafEvent.id)
+      );
+    assert.equal(entryBondAccount.amount.toNumber(), bondAmount);
+  });
+
+  it("sets content limits as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setContentLimits({
+        maxTitleLen: 100,
+        maxUriLen: 128,
+        maxFlairLen: 42,
+        maxBodyLen: 280,
+        // Text posts and comments only
+        entryTypes: (1 << 0) | (1 << 4),
+        allowNsfw: false,
+      })
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const forumConfigAccount =
+      await helpers.compressionProgram.account.forumConfig.fetch(
+        forumConfigPda
+      );
+    assert.equal(forumConfigAccount.contentLimits.maxBodyLen, 280);
+    assert.equal(forumConfigAccount.contentLimits.entryTypes, (1 << 0) | (1 << 4));
+  });
+
+  it("sets uri rules as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setUriRules([
+        {
+          // Link posts only
+          entryTypes: 1 << 2,
+          schemes: ["https"],
+          hosts: ["example.com"],
+        },
+      ])
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const forumConfigAccount =
+      await helpers.compressionProgram.account.forumConfig.fetch(
+        forumConfigPda
+      );
+    assert.equal(forumConfigAccount.uriRules.length, 1);
+    assert.deepEqual(forumConfigAccount.uriRules[0].hosts, ["example.com"]);
+  });
+
+  it("sets strict comments as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setStrictComments({ maxDepth: 2 })
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const forumConfigAccount =
+      await helpers.compressionProgram.account.forumConfig.fetch(
+        forumConfigPda
+      );
+    assert.equal(forumConfigAccount.strictComments.maxDepth, 2);
+  });
+
+  it("locks a thread as a member", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const post = anchor.web3.Keypair.generate().publicKey;
+    const threadLockPda = helpers.findThreadLockPda(
+      merkleTree.publicKey,
+      post
+    );
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .lockThread(post, new anchor.BN(2_000_000_000))
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        threadLock: threadLockPda,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const threadLockAccount =
+      await helpers.compressionProgram.account.threadLock.fetch(threadLockPda);
+    assert.ok(threadLockAccount.post.equals(post));
+    assert.equal(threadLockAccount.until.toNumber(), 2_000_000_000);
+  });
+
+  it("pins an entry as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const entry = anchor.web3.Keypair.generate().publicKey;
+    const forumPinsPda = helpers.findForumPinsPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .pinEntry(entry, 0, null)
+      .accounts({
+        member: admin.publicKey,
+        team: helpers.findTeamPda(merkleTree.publicKey),
+        forumPins: forumPinsPda,
+        forumConfig: helpers.findForumConfigPda(merkleTree.publicKey),
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const forumPinsAccount =
+      await helpers.compressionProgram.account.forumPins.fetch(forumPinsPda);
+    assert.equal(forumPinsAccount.pins.length, 1);
+    assert.ok(forumPinsAccount.pins[0].entry.equals(entry));
+  });
+
+  it("adds an announcement as a member", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const teamPda = helpers.findTeamPda(merkleTree.publicKey);
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setAnnouncementFlair(["test"])
+      .accounts({
+        member: admin.publicKey,
+        team: teamPda,
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        ondaCompression: helpers.compressionProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const leafEvent = await moderationProgram.methods
+      .addAnnouncement(
+        {
+          shortPost: {
+            title: "test",
+            body: "gm",
+            flair: "test",
+            nsfw: false,
+            spoiler: false,
+          },
+        },
+        [],
+        [],
+        []
+      )
+      .accounts({
+        member: admin.publicKey,
+        team: teamPda,
+        ban: helpers.findBanPda(merkleTree.publicKey, admin.publicKey),
+        authorActivity: null,
+        treasury: null,
+        feeMint: null,
+        feeTokenAccount: null,
+        treasuryTokenAccount: null,
+        tokenProgram: null,
+        instructions: null,
+        permitNonce: null,
+        forumConfig: forumConfigPda,
+        merkleTree: merkleTree.publicKey,
+        activeTree: null,
+        entryBond: null,
+        logWrapper: SPL_NOOP_PROGRAM_ID,
+        ondaCompression: helpers.compressionProgram.programId,
+        compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+      })
+      .rpc({ commitment: "confirmed", skipPreflight: true })
+      .then(helpers.getLeafSchemaEvent);
+    assert.ok(leafEvent.author.equals(admin.publicKey));
+  });
+
+  it("sets flair policies as an admin", async () => {
+    const admin = anchor.web3.Keypair.generate();
+    const merkleTree = anchor.web3.Keypair.generate();
+    const forumConfigPda = helpers.findForumConfigPda(merkleTree.publicKey);
+    const accounts = {
+      member: admin.publicKey,
+      team: helpers.findTeamPda(merkleTree.publicKey),
+      forumConfig: forumConfigPda,
+      merkleTree: merkleTree.publicKey,
+      ondaCompression: helpers.compressionProgram.programId,
+    };
+
+    await helpers.requestAirdrop(admin.publicKey);
+    await helpers.initForum(admin, merkleTree);
+    await helpers.initTeam(admin, merkleTree.publicKey);
+
+    // Flair only for holders of the forum's token
+    const moderationProgram = await helpers.getModerationProgram(admin);
+    await moderationProgram.methods
+      .setGates(
+        [
+          {
+            minAmount: new anchor.BN(1),
+            maxAmount: null,
+            decimals: null,
+            address: [anchor.web3.Keypair.generate().publicKey],
+            ruleType: { token: {} },
+            // @ts-ignore
+            operator: { and: {} },
+          },
+        ],
+        []
+      )
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+    await moderationProgram.methods
+      .setFlairPolicies([{ flair: "test", policy: [{ gate: { index: 0 } }] }])
+      .accounts(accounts)
+      .rpc({ commitment: "confirmed" });
+
+    const forumConfigAccount =
+      await helpers.compressionProgram.account.forumConfig.fetch(
+        forumConfigPda
+      );
+    assert.equal(forumConfigAccount.flairPolicies.length, 1);
+    assert.equal(forumConfigAccount.flairPolicies[0].flair, "test");
+  });
 });